@@ -1,9 +1,17 @@
-use std::{collections::HashMap, str::FromStr};
+use std::{
+    cmp::Ordering,
+    collections::{BTreeMap, HashMap},
+    convert::TryFrom,
+    fmt,
+    str::FromStr,
+};
 
 use regex::Regex;
 
+use crate::util::group_records_with_lines;
+
 #[derive(Debug)]
-struct Passport {
+pub struct Passport {
     birth_year: BirthYear,
     issue_year: IssueYear,
     expiration_year: ExpirationYear,
@@ -14,20 +22,34 @@ struct Passport {
     country_id: Option<String>,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ParseMode {
+    Lenient,
+    Strict,
+}
+
 impl FromStr for Passport {
     type Err = PassportValidationError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let kvs: HashMap<&str, &str> = s
-            .split(" ")
-            .filter_map(|x| {
-                if let [k, v, ..] = x.split(":").collect::<Vec<&str>>()[..] {
-                    Some((k, v))
-                } else {
-                    None
-                }
-            })
-            .collect();
+        Passport::parse(s, ParseMode::Lenient)
+    }
+}
+
+impl Passport {
+    pub fn parse(s: &str, mode: ParseMode) -> Result<Passport, PassportValidationError> {
+        Passport::parse_record(s, mode, 1)
+    }
+
+    fn parse_record(
+        s: &str,
+        mode: ParseMode,
+        first_line: usize,
+    ) -> Result<Passport, PassportValidationError> {
+        let kvs = match mode {
+            ParseMode::Lenient => parse_fields(s),
+            ParseMode::Strict => parse_fields_strictly(s, first_line)?,
+        };
         Ok(Passport {
             birth_year: kvs
                 .get("byr")
@@ -41,10 +63,10 @@ impl FromStr for Passport {
                 .get("eyr")
                 .ok_or(PassportValidationError::FieldMissing("eyr"))?
                 .parse()?,
-            height: kvs
-                .get("hgt")
-                .ok_or(PassportValidationError::FieldMissing("hgt"))?
-                .parse()?,
+            height: validate_passport_height(
+                kvs.get("hgt")
+                    .ok_or(PassportValidationError::FieldMissing("hgt"))?,
+            )?,
             hair_color: kvs
                 .get("hcl")
                 .ok_or(PassportValidationError::FieldMissing("hcl"))?
@@ -62,6 +84,61 @@ impl FromStr for Passport {
     }
 }
 
+const FIELDS: [&str; 8] = ["byr", "iyr", "eyr", "hgt", "hcl", "ecl", "pid", "cid"];
+
+fn parse_fields(s: &str) -> HashMap<&str, &str> {
+    s.split([' ', '\n'])
+        .filter_map(|x| {
+            if let [k, v, ..] = x.split(':').collect::<Vec<&str>>()[..] {
+                Some((k, v))
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+fn parse_fields_strictly(
+    s: &str,
+    first_line: usize,
+) -> Result<HashMap<&str, &str>, PassportValidationError> {
+    let mut kvs = HashMap::new();
+    for (line, text) in s.lines().enumerate().map(|(i, x)| (first_line + i, x)) {
+        let mut column = 1;
+        for token in text.split(' ') {
+            if !token.is_empty() {
+                match token.split(':').collect::<Vec<&str>>()[..] {
+                    [k, v] if !k.is_empty() && !v.is_empty() => {
+                        if !FIELDS.contains(&k) {
+                            return Err(PassportValidationError::UnknownField {
+                                key: k.to_string(),
+                                line,
+                                column,
+                            });
+                        }
+                        if kvs.insert(k, v).is_some() {
+                            return Err(PassportValidationError::DuplicateField {
+                                key: k.to_string(),
+                                line,
+                                column,
+                            });
+                        }
+                    }
+                    _ => {
+                        return Err(PassportValidationError::MalformedToken {
+                            token: token.to_string(),
+                            line,
+                            column,
+                        })
+                    }
+                }
+            }
+            column += token.chars().count() + 1;
+        }
+    }
+    Ok(kvs)
+}
+
 #[derive(Debug)]
 struct BirthYear {
     value: u16,
@@ -122,62 +199,159 @@ impl FromStr for ExpirationYear {
     }
 }
 
-#[derive(Debug)]
-struct Height {
+#[derive(Debug, Clone, Copy)]
+pub struct Height {
     amount: u16,
     unit: LengthUnit,
 }
 
-#[derive(Debug)]
-enum LengthUnit {
-    Inches,
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum LengthUnit {
+    Millimetres,
     Centimetres,
+    Inches,
+    FeetAndInches,
+}
+
+impl LengthUnit {
+    fn tenths_of_millimetre(self) -> u32 {
+        match self {
+            LengthUnit::Millimetres => 10,
+            LengthUnit::Centimetres => 100,
+            LengthUnit::Inches | LengthUnit::FeetAndInches => 254,
+        }
+    }
 }
 
 #[derive(Debug)]
-struct LengthUnitParseError;
+pub struct LengthUnitParseError;
 
 impl FromStr for LengthUnit {
     type Err = LengthUnitParseError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         match s {
-            "in" => Ok(LengthUnit::Inches),
+            "mm" => Ok(LengthUnit::Millimetres),
             "cm" => Ok(LengthUnit::Centimetres),
+            "in" => Ok(LengthUnit::Inches),
             _ => Err(LengthUnitParseError),
         }
     }
 }
 
+#[derive(Debug, PartialEq)]
+pub struct HeightParseError(&'static str);
+
+impl Height {
+    pub fn new(amount: u16, unit: LengthUnit) -> Height {
+        Height { amount, unit }
+    }
+
+    pub fn amount(&self) -> u16 {
+        self.amount
+    }
+
+    pub fn unit(&self) -> LengthUnit {
+        self.unit
+    }
+
+    fn in_tenths_of_millimetre(&self) -> u32 {
+        u32::from(self.amount) * self.unit.tenths_of_millimetre()
+    }
+
+    pub fn convert_to(&self, unit: LengthUnit) -> Option<Height> {
+        let factor = unit.tenths_of_millimetre();
+        let amount = (self.in_tenths_of_millimetre() + factor / 2) / factor;
+        Some(Height {
+            amount: u16::try_from(amount).ok()?,
+            unit,
+        })
+    }
+}
+
+impl PartialEq for Height {
+    fn eq(&self, other: &Self) -> bool {
+        self.in_tenths_of_millimetre() == other.in_tenths_of_millimetre()
+    }
+}
+
+impl Eq for Height {}
+
+impl PartialOrd for Height {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Height {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.in_tenths_of_millimetre()
+            .cmp(&other.in_tenths_of_millimetre())
+    }
+}
+
+impl fmt::Display for Height {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.unit {
+            LengthUnit::Millimetres => write!(f, "{}mm", self.amount),
+            LengthUnit::Centimetres => write!(f, "{}cm", self.amount),
+            LengthUnit::Inches => write!(f, "{}in", self.amount),
+            LengthUnit::FeetAndInches => {
+                write!(f, "{}'{}\"", self.amount / 12, self.amount % 12)
+            }
+        }
+    }
+}
+
 impl FromStr for Height {
-    type Err = PassportValidationError;
+    type Err = HeightParseError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let parse_unit = s[s.len() - 2..].parse::<LengthUnit>();
-        let parse_result = s[..s.len() - 2].parse::<u16>();
-
-        if let Ok(amount) = parse_result {
-            if let Ok(unit) = parse_unit {
-                match unit {
-                    LengthUnit::Centimetres => {
-                        if (amount >= 150) & (amount <= 193) {
-                            return Ok(Height {
-                                unit: unit,
-                                amount: amount,
-                            });
-                        }
-                    }
-                    LengthUnit::Inches => {
-                        if (amount >= 59) & (amount <= 76) {
-                            return Ok(Height {
-                                unit: unit,
-                                amount: amount,
-                            });
-                        }
-                    }
-                }
+        fn parse_amount(s: &str) -> Result<u16, HeightParseError> {
+            if s.is_empty() || !s.bytes().all(|x| x.is_ascii_digit()) {
+                return Err(HeightParseError("Amount is not a number"));
             }
+            s.parse().or(Err(HeightParseError("Amount is too large")))
         }
+
+        if let Some((feet, rest)) = s.split_once('\'') {
+            let feet = parse_amount(feet)?;
+            let inches = match rest.strip_suffix('"') {
+                Some(inches) => parse_amount(inches)?,
+                None if rest.is_empty() => 0,
+                None => return Err(HeightParseError("Inches must end with '\"'")),
+            };
+            if inches >= 12 {
+                return Err(HeightParseError("Inches must be less than 12"));
+            }
+            let amount = feet
+                .checked_mul(12)
+                .and_then(|x| x.checked_add(inches))
+                .ok_or(HeightParseError("Amount is too large"))?;
+            return Ok(Height::new(amount, LengthUnit::FeetAndInches));
+        }
+
+        let split_at = s
+            .find(|x: char| !x.is_ascii_digit())
+            .ok_or(HeightParseError("Missing unit"))?;
+        let (amount, unit) = s.split_at(split_at);
+        let unit = unit.parse().or(Err(HeightParseError("Unknown unit")))?;
+        Ok(Height::new(parse_amount(amount)?, unit))
+    }
+}
+
+fn validate_passport_height(s: &str) -> Result<Height, PassportValidationError> {
+    let height: Height = s
+        .parse()
+        .or(Err(PassportValidationError::FieldInvalid("Height")))?;
+    let valid = match height.unit {
+        LengthUnit::Centimetres => (150..=193).contains(&height.amount),
+        LengthUnit::Inches => (59..=76).contains(&height.amount),
+        _ => false,
+    };
+    if valid {
+        Ok(height)
+    } else {
         Err(PassportValidationError::FieldInvalid("Height"))
     }
 }
@@ -250,37 +424,141 @@ impl FromStr for PassportId {
     }
 }
 
-#[derive(Debug)]
-enum PassportValidationError {
+#[derive(Debug, PartialEq)]
+pub enum PassportValidationError {
     FieldMissing(&'static str),
     FieldInvalid(&'static str),
+    UnknownField {
+        key: String,
+        line: usize,
+        column: usize,
+    },
+    DuplicateField {
+        key: String,
+        line: usize,
+        column: usize,
+    },
+    MalformedToken {
+        token: String,
+        line: usize,
+        column: usize,
+    },
 }
 
-fn group_batch_file_lines(input: Vec<String>) -> Vec<String> {
-    input
-        .iter()
-        .fold(vec![vec![]], |mut acc, line| {
-            if let Some(current_batch) = acc.last_mut() {
-                if line == "" {
-                    acc.push(vec![])
-                } else {
-                    current_batch.push(line.clone())
-                }
-            }
-            acc
-        })
-        .iter()
-        .map(|x| x.join(" "))
+fn group_batch_file_lines(input: &[&str], separator: &str) -> Vec<(usize, String)> {
+    group_records_with_lines(input)
+        .into_iter()
+        .map(|(first_line, lines)| (first_line, lines.join(separator)))
         .collect()
 }
 
-fn parse_batch_files(input: Vec<&str>) -> Vec<Result<Passport, PassportValidationError>> {
-    group_batch_file_lines(input.iter().map(|x| x.to_string()).collect())
+pub fn parse_batch_files(input: Vec<&str>) -> Vec<Result<Passport, PassportValidationError>> {
+    parse_batch_files_with_mode(input, ParseMode::Lenient)
+}
+
+pub fn parse_batch_files_with_mode(
+    input: Vec<&str>,
+    mode: ParseMode,
+) -> Vec<Result<Passport, PassportValidationError>> {
+    group_batch_file_lines(&input, "\n")
         .iter()
-        .map(|x| x.parse())
+        .map(|(first_line, record)| Passport::parse_record(record, mode, *first_line))
         .collect()
 }
 
+#[derive(Debug, Default)]
+pub struct BatchStatistics {
+    pub records: usize,
+    pub valid: usize,
+    pub missing_fields: BTreeMap<&'static str, usize>,
+    pub eye_colors: BTreeMap<String, usize>,
+    pub height_units: BTreeMap<LengthUnit, usize>,
+    pub unparseable_heights: usize,
+    pub year_ranges: BTreeMap<&'static str, (u16, u16)>,
+    pub unknown_keys: usize,
+    pub duplicate_passport_ids: BTreeMap<String, Vec<usize>>,
+}
+
+pub fn batch_statistics(input: Vec<&str>) -> BatchStatistics {
+    let records = group_batch_file_lines(&input, " ");
+    let mut statistics = BatchStatistics {
+        records: records.len(),
+        valid: parse_batch_files(input)
+            .iter()
+            .filter(|x| x.is_ok())
+            .count(),
+        ..Default::default()
+    };
+    let mut passport_ids: BTreeMap<String, Vec<usize>> = BTreeMap::new();
+    for (i, (_, record)) in records.iter().enumerate() {
+        let kvs = parse_fields(record);
+        for field in FIELDS.iter() {
+            if !kvs.contains_key(field) {
+                *statistics.missing_fields.entry(field).or_insert(0) += 1;
+            }
+        }
+        statistics.unknown_keys += kvs.keys().filter(|k| !FIELDS.contains(k)).count();
+        if let Some(eye_color) = kvs.get("ecl") {
+            *statistics
+                .eye_colors
+                .entry(eye_color.to_string())
+                .or_insert(0) += 1;
+        }
+        if let Some(height) = kvs.get("hgt") {
+            match height.parse::<Height>() {
+                Ok(height) => *statistics.height_units.entry(height.unit()).or_insert(0) += 1,
+                Err(_) => statistics.unparseable_heights += 1,
+            }
+        }
+        for field in ["byr", "iyr", "eyr"].iter() {
+            if let Some(Ok(year)) = kvs.get(field).map(|x| x.parse::<u16>()) {
+                let range = statistics.year_ranges.entry(field).or_insert((year, year));
+                *range = (range.0.min(year), range.1.max(year));
+            }
+        }
+        if let Some(passport_id) = kvs.get("pid") {
+            passport_ids
+                .entry(passport_id.to_string())
+                .or_default()
+                .push(i);
+        }
+    }
+    statistics.duplicate_passport_ids = passport_ids
+        .into_iter()
+        .filter(|(_, records)| records.len() > 1)
+        .collect();
+    statistics
+}
+
+impl fmt::Display for BatchStatistics {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "records: {} ({} valid)", self.records, self.valid)?;
+        writeln!(f, "missing fields:")?;
+        for (field, count) in self.missing_fields.iter() {
+            writeln!(f, "  {}: {}", field, count)?;
+        }
+        writeln!(f, "eye colours:")?;
+        for (eye_color, count) in self.eye_colors.iter() {
+            writeln!(f, "  {}: {}", eye_color, count)?;
+        }
+        writeln!(f, "height units:")?;
+        for (unit, count) in self.height_units.iter() {
+            writeln!(f, "  {:?}: {}", unit, count)?;
+        }
+        writeln!(f, "  unparseable: {}", self.unparseable_heights)?;
+        writeln!(f, "year ranges:")?;
+        for (field, (min, max)) in self.year_ranges.iter() {
+            writeln!(f, "  {}: {}-{}", field, min, max)?;
+        }
+        writeln!(f, "unknown keys: {}", self.unknown_keys)?;
+        writeln!(f, "duplicate pids:")?;
+        for (passport_id, records) in self.duplicate_passport_ids.iter() {
+            writeln!(f, "  {}: records {:?}", passport_id, records)?;
+        }
+        Ok(())
+    }
+}
+
 pub fn puzzle1(input: Vec<&str>) -> usize {
     parse_batch_files(input)
         .iter()
@@ -302,6 +580,141 @@ mod tests {
         assert_eq!(puzzle1(REAL_INPUT.to_vec()), 137);
     }
 
+    #[test]
+    fn strict_parsing() {
+        let valid = "ecl:gry pid:860033327 eyr:2020 hcl:#fffffd byr:1937 iyr:2017 hgt:183cm";
+        assert!(Passport::parse(valid, ParseMode::Strict).is_ok());
+        assert_eq!(
+            Passport::parse(&format!("{} foo:bar", valid), ParseMode::Strict).unwrap_err(),
+            PassportValidationError::UnknownField {
+                key: "foo".to_string(),
+                line: 1,
+                column: valid.len() + 2
+            }
+        );
+        assert!(Passport::parse(&format!("{} foo:bar", valid), ParseMode::Lenient).is_ok());
+        assert_eq!(
+            Passport::parse(&format!("ecl:amb {}", valid), ParseMode::Strict).unwrap_err(),
+            PassportValidationError::DuplicateField {
+                key: "ecl".to_string(),
+                line: 1,
+                column: 9
+            }
+        );
+        assert_eq!(
+            Passport::parse(&format!("cid {}", valid), ParseMode::Strict).unwrap_err(),
+            PassportValidationError::MalformedToken {
+                token: "cid".to_string(),
+                line: 1,
+                column: 1
+            }
+        );
+        assert_eq!(
+            Passport::parse(&format!("{} cid:1:2", valid), ParseMode::Strict).unwrap_err(),
+            PassportValidationError::MalformedToken {
+                token: "cid:1:2".to_string(),
+                line: 1,
+                column: valid.len() + 2
+            }
+        );
+        assert_eq!(
+            parse_batch_files_with_mode(TEST_INPUT.to_vec(), ParseMode::Strict)
+                .iter()
+                .filter(|x| x.is_ok())
+                .count(),
+            2
+        );
+    }
+
+    #[test]
+    fn strict_parsing_reports_file_positions() {
+        let input = vec![
+            "ecl:gry pid:860033327 eyr:2020 hcl:#fffffd",
+            "byr:1937 iyr:2017 cid:147 hgt:183cm",
+            "",
+            "iyr:2013 ecl:amb cid:350 eyr:2023 pid:028048884",
+            "hcl:#cfa07d  byr:1929 foo:bar",
+            "hgt:150cm",
+        ];
+        let results = parse_batch_files_with_mode(input, ParseMode::Strict);
+        assert!(results[0].is_ok());
+        assert_eq!(
+            results[1].as_ref().unwrap_err(),
+            &PassportValidationError::UnknownField {
+                key: "foo".to_string(),
+                line: 5,
+                column: 23
+            }
+        );
+    }
+
+    #[test]
+    fn batch_statistics_test_input() {
+        let mut input = TEST_INPUT.to_vec();
+        input.extend(&["", "pid:860033327 hgt:70 foo:bar"]);
+        let statistics = batch_statistics(input);
+        assert_eq!(statistics.records, 5);
+        assert_eq!(statistics.valid, 2);
+        assert_eq!(statistics.missing_fields.get("hgt"), Some(&1));
+        assert_eq!(statistics.missing_fields.get("cid"), Some(&3));
+        assert_eq!(statistics.missing_fields.get("pid"), None);
+        assert_eq!(statistics.eye_colors.get("brn"), Some(&2));
+        assert_eq!(
+            statistics.height_units.get(&LengthUnit::Centimetres),
+            Some(&2)
+        );
+        assert_eq!(statistics.unparseable_heights, 1);
+        assert_eq!(statistics.year_ranges.get("byr"), Some(&(1929, 1937)));
+        assert_eq!(statistics.unknown_keys, 1);
+        assert_eq!(
+            statistics.duplicate_passport_ids.get("860033327"),
+            Some(&vec![0, 4])
+        );
+    }
+
+    #[test]
+    fn height_parsing() {
+        assert_eq!(
+            "183cm".parse(),
+            Ok(Height::new(183, LengthUnit::Centimetres))
+        );
+        assert_eq!("5'11\"".parse::<Height>().unwrap().amount(), 71);
+        assert_eq!("6'".parse::<Height>().unwrap().amount(), 72);
+        assert!("".parse::<Height>().is_err());
+        assert!("c".parse::<Height>().is_err());
+        assert!("cm".parse::<Height>().is_err());
+        assert!("12".parse::<Height>().is_err());
+        assert!("5'12\"".parse::<Height>().is_err());
+        assert!("1é".parse::<Height>().is_err());
+        assert!("99999cm".parse::<Height>().is_err());
+    }
+
+    #[test]
+    fn height_conversion_and_ordering() {
+        let height = Height::new(72, LengthUnit::Inches);
+        assert_eq!(
+            height.convert_to(LengthUnit::Centimetres),
+            Some(Height::new(183, LengthUnit::Centimetres))
+        );
+        assert_eq!(
+            height
+                .convert_to(LengthUnit::FeetAndInches)
+                .unwrap()
+                .to_string(),
+            "6'0\""
+        );
+        assert_eq!(
+            Height::new(10, LengthUnit::Millimetres),
+            Height::new(1, LengthUnit::Centimetres)
+        );
+        assert!(Height::new(1, LengthUnit::Inches) > Height::new(2, LengthUnit::Centimetres));
+        assert!(Height::new(1, LengthUnit::Inches) < Height::new(26, LengthUnit::Millimetres));
+        assert_eq!(
+            Height::new(60000, LengthUnit::Inches).convert_to(LengthUnit::Millimetres),
+            None
+        );
+    }
+
     const TEST_INPUT: &'static [&'static str] = &[
         "ecl:gry pid:860033327 eyr:2020 hcl:#fffffd",
         "byr:1937 iyr:2017 cid:147 hgt:183cm",
@@ -1,178 +1,556 @@
-use std::str::FromStr;
+use std::{
+    collections::{BTreeSet, HashSet},
+    fmt,
+    str::FromStr,
+};
 
 #[derive(Debug)]
-struct BoardingPass {
-    row_instructions: [RowInstruction; 7],
-    column_instructions: [ColumnInstruction; 3],
+pub struct BoardingPass {
+    row_instructions: Vec<RowInstruction>,
+    column_instructions: Vec<ColumnInstruction>,
+    row: usize,
+    column: usize,
 }
 
-#[derive(Debug)]
-struct BoardingPassParseError {
-    reason: &'static str,
+#[derive(Debug, PartialEq)]
+pub struct BoardingPassParseError {
+    pub reason: &'static str,
+    pub position: usize,
+}
+
+impl fmt::Display for BoardingPassParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} at position {}", self.reason, self.position)
+    }
 }
 
 impl FromStr for BoardingPass {
     type Err = BoardingPassParseError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
+        BspDecoder::default().parse(s)
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct BspDecoderError(&'static str);
+
+#[derive(Debug, Clone)]
+pub struct BspDecoder {
+    rows: usize,
+    columns: usize,
+    row_characters: (char, char),
+    column_characters: (char, char),
+    column_multiplier: usize,
+}
+
+impl Default for BspDecoder {
+    fn default() -> Self {
+        BspDecoder {
+            rows: 7,
+            columns: 3,
+            row_characters: ('F', 'B'),
+            column_characters: ('L', 'R'),
+            column_multiplier: 8,
+        }
+    }
+}
+
+impl BspDecoder {
+    pub fn new(rows: usize, columns: usize) -> Result<BspDecoder, BspDecoderError> {
+        if rows.saturating_add(columns) >= usize::BITS as usize {
+            return Err(BspDecoderError("Seat IDs would not fit in a usize"));
+        }
+        Ok(BspDecoder {
+            rows,
+            columns,
+            column_multiplier: 1 << columns,
+            ..Default::default()
+        })
+    }
+
+    pub fn with_row_characters(mut self, front: char, back: char) -> BspDecoder {
+        self.row_characters = (front, back);
+        self
+    }
+
+    pub fn with_column_characters(mut self, left: char, right: char) -> BspDecoder {
+        self.column_characters = (left, right);
+        self
+    }
+
+    pub fn with_column_multiplier(
+        mut self,
+        column_multiplier: usize,
+    ) -> Result<BspDecoder, BspDecoderError> {
+        let max_row: usize = (1 << self.rows) - 1;
+        let max_column: usize = (1 << self.columns) - 1;
+        if column_multiplier <= max_column {
+            return Err(BspDecoderError("Column multiplier is too small"));
+        }
+        if max_row
+            .checked_mul(column_multiplier)
+            .and_then(|x| x.checked_add(max_column))
+            .is_none()
+        {
+            return Err(BspDecoderError("Seat IDs would not fit in a usize"));
+        }
+        self.column_multiplier = column_multiplier;
+        Ok(self)
+    }
+
+    pub fn parse(&self, s: &str) -> Result<BoardingPass, BoardingPassParseError> {
+        let characters = s.chars().collect::<Vec<_>>();
+        let expected_length = self.rows + self.columns;
+        if characters.len() < expected_length {
+            return Err(BoardingPassParseError {
+                reason: "Too few instructions",
+                position: characters.len(),
+            });
+        } else if characters.len() > expected_length {
+            return Err(BoardingPassParseError {
+                reason: "Too many instructions",
+                position: expected_length,
+            });
+        }
+        let row_instructions: Vec<RowInstruction> = characters[..self.rows]
+            .iter()
+            .enumerate()
+            .map(|(position, &x)| match x {
+                x if x == self.row_characters.0 => Ok(RowInstruction::Front),
+                x if x == self.row_characters.1 => Ok(RowInstruction::Back),
+                _ => Err(BoardingPassParseError {
+                    reason: "Unknown Row Instruction",
+                    position,
+                }),
+            })
+            .collect::<Result<_, _>>()?;
+        let column_instructions: Vec<ColumnInstruction> = characters[self.rows..]
+            .iter()
+            .enumerate()
+            .map(|(i, &x)| match x {
+                x if x == self.column_characters.0 => Ok(ColumnInstruction::Left),
+                x if x == self.column_characters.1 => Ok(ColumnInstruction::Right),
+                _ => Err(BoardingPassParseError {
+                    reason: "Unknown Column Instruction",
+                    position: self.rows + i,
+                }),
+            })
+            .collect::<Result<_, _>>()?;
         Ok(BoardingPass {
-            row_instructions: s[..7]
-                .chars()
-                .map(|x| x.to_string().parse())
-                .collect::<Result<Vec<_>, _>>()?
+            row: row_instructions
                 .iter()
-                .enumerate()
-                .fold([RowInstruction::Front; 7], |mut acc, (i, &x)| {
-                    if i < acc.len() {
-                        acc[i] = x;
-                    }
-                    acc
-                }),
-            column_instructions: s[7..]
-                .chars()
-                .map(|x| x.to_string().parse())
-                .collect::<Result<Vec<_>, _>>()?
+                .fold(0, |acc, x| acc << 1 | *x as usize),
+            column: column_instructions
                 .iter()
-                .enumerate()
-                .fold([ColumnInstruction::Left; 3], |mut acc, (i, &x)| {
-                    if i < acc.len() {
-                        acc[i] = x;
-                    }
-                    acc
-                }),
+                .fold(0, |acc, x| acc << 1 | *x as usize),
+            row_instructions,
+            column_instructions,
         })
     }
+
+    pub fn parse_all(
+        &self,
+        input: &[String],
+    ) -> Result<Vec<BoardingPass>, Vec<(usize, BoardingPassParseError)>> {
+        let mut boarding_passes = vec![];
+        let mut errors = vec![];
+        for (line, s) in input.iter().enumerate().map(|(i, x)| (i + 1, x)) {
+            match self.parse(s) {
+                Ok(boarding_pass) => boarding_passes.push(boarding_pass),
+                Err(error) => errors.push((line, error)),
+            }
+        }
+        if errors.is_empty() {
+            Ok(boarding_passes)
+        } else {
+            Err(errors)
+        }
+    }
+
+    pub fn decode(&self, s: &str) -> Result<(usize, usize), BoardingPassParseError> {
+        let position = self.parse(s)?.to_seating_position();
+        Ok((position.row, position.column))
+    }
+
+    pub fn seat_id(&self, row: usize, column: usize) -> usize {
+        row * self.column_multiplier + column
+    }
+
+    pub fn decode_seat_id(&self, s: &str) -> Result<usize, BoardingPassParseError> {
+        let (row, column) = self.decode(s)?;
+        Ok(self.seat_id(row, column))
+    }
+
+    pub fn position_from_seat_id(&self, id: usize) -> SeatingPosition {
+        SeatingPosition::from_id_with_multiplier(id, self.column_multiplier)
+    }
+
+    pub fn encode(&self, row: usize, column: usize) -> Option<String> {
+        if row >> self.rows != 0 || column >> self.columns != 0 {
+            return None;
+        }
+        let encode_bits = |value: usize, length: usize, (lower, upper): (char, char)| {
+            (0..length)
+                .rev()
+                .map(move |i| if value >> i & 1 == 1 { upper } else { lower })
+        };
+        Some(
+            encode_bits(row, self.rows, self.row_characters)
+                .chain(encode_bits(column, self.columns, self.column_characters))
+                .collect(),
+        )
+    }
 }
 
-trait ToSeatingPosition {
+pub trait ToSeatingPosition {
     fn to_seating_position(&self) -> SeatingPosition;
 }
 
+impl BoardingPass {
+    pub fn to_seating_position_bitwise(&self) -> SeatingPosition {
+        SeatingPosition {
+            row: self.row,
+            column: self.column,
+            id: self.row << self.column_instructions.len() | self.column,
+        }
+    }
+}
+
 impl ToSeatingPosition for BoardingPass {
     fn to_seating_position(&self) -> SeatingPosition {
-        fn calculate_row(instructions: std::slice::Iter<RowInstruction>) -> usize {
-            instructions
-                .fold((0, 127), |(lower, upper), item| {
-                    let span = upper - lower;
-                    if span > 1 {
-                        match item {
-                            RowInstruction::Back => (lower + span / 2 + 1, upper),
-                            RowInstruction::Front => (lower, lower + span / 2),
-                        }
-                    } else {
-                        match item {
-                            RowInstruction::Back => (upper, upper),
-                            RowInstruction::Front => (lower, lower),
-                        }
-                    }
-                })
-                .0
-        }
-        fn calculate_column(instructions: std::slice::Iter<ColumnInstruction>) -> usize {
-            instructions
-                .fold((0, 7), |(lower, upper), item| {
+        fn calculate_position(upper_halves: impl ExactSizeIterator<Item = bool>) -> usize {
+            let upper_bound = (1 << upper_halves.len()) - 1;
+            upper_halves
+                .fold((0, upper_bound), |(lower, upper), is_upper_half| {
                     let span = upper - lower;
                     if span > 1 {
-                        match item {
-                            ColumnInstruction::Right => (lower + span / 2 + 1, upper),
-                            ColumnInstruction::Left => (lower, lower + span / 2),
+                        if is_upper_half {
+                            (lower + span / 2 + 1, upper)
+                        } else {
+                            (lower, lower + span / 2)
                         }
+                    } else if is_upper_half {
+                        (upper, upper)
                     } else {
-                        match item {
-                            ColumnInstruction::Right => (upper, upper),
-                            ColumnInstruction::Left => (lower, lower),
-                        }
+                        (lower, lower)
                     }
                 })
                 .0
         }
-        let row = calculate_row(self.row_instructions.iter());
-        let column = calculate_column(self.column_instructions.iter());
+        let row = calculate_position(
+            self.row_instructions
+                .iter()
+                .map(|x| matches!(x, RowInstruction::Back)),
+        );
+        let column = calculate_position(
+            self.column_instructions
+                .iter()
+                .map(|x| matches!(x, ColumnInstruction::Right)),
+        );
         SeatingPosition {
             row,
             column,
-            id: row * 8 + column,
+            id: row * (1 << self.column_instructions.len()) + column,
         }
     }
 }
 
 #[derive(Debug, Copy, Clone)]
 enum RowInstruction {
-    Front,
-    Back,
-}
-
-impl FromStr for RowInstruction {
-    type Err = BoardingPassParseError;
-
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        match s {
-            "F" => Ok(RowInstruction::Front),
-            "B" => Ok(RowInstruction::Back),
-            _ => Err(BoardingPassParseError {
-                reason: "Unknown Row Instruction",
-            }),
-        }
-    }
+    Front = 0,
+    Back = 1,
 }
 
 #[derive(Debug, Copy, Clone)]
 enum ColumnInstruction {
-    Left,
-    Right,
+    Left = 0,
+    Right = 1,
 }
 
-impl FromStr for ColumnInstruction {
-    type Err = BoardingPassParseError;
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SeatingPosition {
+    pub row: usize,
+    pub column: usize,
+    pub id: usize,
+}
 
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        match s {
-            "L" => Ok(ColumnInstruction::Left),
-            "R" => Ok(ColumnInstruction::Right),
-            _ => Err(BoardingPassParseError {
-                reason: "Unknown Column Instruction",
-            }),
+impl SeatingPosition {
+    pub fn new(row: usize, column: usize) -> SeatingPosition {
+        SeatingPosition {
+            row,
+            column,
+            id: row * 8 + column,
+        }
+    }
+
+    pub fn from_id(id: usize) -> SeatingPosition {
+        SeatingPosition::from_id_with_multiplier(id, 8)
+    }
+
+    pub fn from_id_with_multiplier(id: usize, column_multiplier: usize) -> SeatingPosition {
+        SeatingPosition {
+            row: id / column_multiplier,
+            column: id % column_multiplier,
+            id,
         }
     }
 }
 
-#[derive(Debug)]
-struct SeatingPosition {
-    row: usize,
-    column: usize,
-    id: usize,
+impl fmt::Display for SeatingPosition {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "row {}, column {}, seat ID {}",
+            self.row, self.column, self.id
+        )
+    }
 }
 
 pub fn puzzle1(input: Vec<String>) -> usize {
-    input
-        .iter()
-        .map(|x| x.parse::<BoardingPass>())
-        .collect::<Result<Vec<_>, _>>()
+    BspDecoder::default()
+        .parse_all(&input)
         .unwrap()
         .iter()
         .map(|x| x.to_seating_position().id)
         .max()
         .unwrap()
 }
-pub fn puzzle2(input: Vec<String>) -> usize {
-    let mut all_boarding_passes = input
-        .iter()
-        .map(|x| x.parse::<BoardingPass>())
-        .collect::<Result<Vec<_>, _>>()
-        .unwrap()
-        .iter()
-        .map(|x| x.to_seating_position().id)
-        .collect::<Vec<_>>();
-    all_boarding_passes.sort_unstable();
-    let mut prev = all_boarding_passes.remove(0);
-    for id in all_boarding_passes.iter() {
-        if id - prev == 2 {
-            return prev + 1;
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EmptySeatKind {
+    Front,
+    Gap,
+    Back,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum LayoutDecodeError {
+    Parse(BoardingPassParseError),
+    Decoder(BspDecoderError),
+    RowOutOfLayout { row: usize },
+    ColumnOutOfLayout { column: usize },
+    SeatBlocked { row: usize, column: usize },
+}
+
+impl From<BoardingPassParseError> for LayoutDecodeError {
+    fn from(error: BoardingPassParseError) -> Self {
+        LayoutDecodeError::Parse(error)
+    }
+}
+
+impl From<BspDecoderError> for LayoutDecodeError {
+    fn from(error: BspDecoderError) -> Self {
+        LayoutDecodeError::Decoder(error)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct PlaneLayout {
+    rows: usize,
+    columns: usize,
+    blocked_seats: HashSet<(usize, usize)>,
+    aisles: BTreeSet<usize>,
+}
+
+impl Default for PlaneLayout {
+    fn default() -> Self {
+        PlaneLayout::new(128, 8)
+    }
+}
+
+impl PlaneLayout {
+    pub fn new(rows: usize, columns: usize) -> PlaneLayout {
+        PlaneLayout {
+            rows,
+            columns,
+            blocked_seats: HashSet::new(),
+            aisles: BTreeSet::new(),
+        }
+    }
+
+    pub fn with_blocked_seat(mut self, row: usize, column: usize) -> PlaneLayout {
+        self.blocked_seats.insert((row, column));
+        self
+    }
+
+    pub fn with_aisle_after(mut self, column: usize) -> PlaneLayout {
+        self.aisles.insert(column);
+        self
+    }
+
+    pub fn has_seat(&self, row: usize, column: usize) -> bool {
+        row < self.rows && column < self.columns && !self.blocked_seats.contains(&(row, column))
+    }
+
+    pub fn decoder(&self) -> Result<BspDecoder, BspDecoderError> {
+        fn bits_needed(count: usize) -> usize {
+            (usize::BITS - count.saturating_sub(1).leading_zeros()) as usize
+        }
+        BspDecoder::new(bits_needed(self.rows), bits_needed(self.columns))
+    }
+
+    pub fn decode(&self, s: &str) -> Result<SeatingPosition, LayoutDecodeError> {
+        let (row, column) = self.decoder()?.decode(s)?;
+        if row >= self.rows {
+            Err(LayoutDecodeError::RowOutOfLayout { row })
+        } else if column >= self.columns {
+            Err(LayoutDecodeError::ColumnOutOfLayout { column })
+        } else if !self.has_seat(row, column) {
+            Err(LayoutDecodeError::SeatBlocked { row, column })
         } else {
-            prev = *id;
+            Ok(SeatingPosition {
+                row,
+                column,
+                id: row * self.columns + column,
+            })
         }
     }
-    panic!("No ID found")
+}
+
+#[derive(Debug)]
+pub struct SeatMap {
+    layout: PlaneLayout,
+    seats: Vec<Vec<Vec<usize>>>,
+    rejected: Vec<usize>,
+}
+
+impl SeatMap {
+    pub fn new(boarding_passes: &[BoardingPass]) -> SeatMap {
+        SeatMap::with_layout(PlaneLayout::default(), boarding_passes)
+    }
+
+    pub fn with_layout(layout: PlaneLayout, boarding_passes: &[BoardingPass]) -> SeatMap {
+        let mut seats = vec![vec![vec![]; layout.columns]; layout.rows];
+        let mut rejected = vec![];
+        for (i, boarding_pass) in boarding_passes.iter().enumerate() {
+            let position = boarding_pass.to_seating_position();
+            if layout.has_seat(position.row, position.column) {
+                seats[position.row][position.column].push(i);
+            } else {
+                rejected.push(i);
+            }
+        }
+        SeatMap {
+            layout,
+            seats,
+            rejected,
+        }
+    }
+
+    pub fn rejected(&self) -> &[usize] {
+        &self.rejected
+    }
+
+    pub fn is_occupied(&self, row: usize, column: usize) -> bool {
+        self.seats
+            .get(row)
+            .and_then(|x| x.get(column))
+            .is_some_and(|x| !x.is_empty())
+    }
+
+    fn seat_id(&self, row: usize, column: usize) -> usize {
+        row * self.layout.columns + column
+    }
+
+    fn occupied_seat_ids(&self) -> impl Iterator<Item = usize> + '_ {
+        self.seats.iter().enumerate().flat_map(move |(row, seats)| {
+            seats
+                .iter()
+                .enumerate()
+                .filter(|(_, passes)| !passes.is_empty())
+                .map(move |(column, _)| self.seat_id(row, column))
+        })
+    }
+
+    pub fn empty_seats(&self) -> Vec<(usize, usize, EmptySeatKind)> {
+        let first = self.occupied_seat_ids().next();
+        let last = self.occupied_seat_ids().last();
+        let mut result = vec![];
+        for row in 0..self.layout.rows {
+            for column in 0..self.layout.columns {
+                if !self.layout.has_seat(row, column) || self.is_occupied(row, column) {
+                    continue;
+                }
+                let id = self.seat_id(row, column);
+                let kind = match (first, last) {
+                    (Some(first), _) if id < first => EmptySeatKind::Front,
+                    (_, Some(last)) if id > last => EmptySeatKind::Back,
+                    (Some(_), Some(_)) => EmptySeatKind::Gap,
+                    _ => EmptySeatKind::Front,
+                };
+                result.push((row, column, kind));
+            }
+        }
+        result
+    }
+
+    pub fn missing_front_rows(&self) -> usize {
+        self.seats
+            .iter()
+            .take_while(|row| row.iter().all(|x| x.is_empty()))
+            .count()
+    }
+
+    pub fn missing_back_rows(&self) -> usize {
+        if self.missing_front_rows() == self.layout.rows {
+            return 0;
+        }
+        self.seats
+            .iter()
+            .rev()
+            .take_while(|row| row.iter().all(|x| x.is_empty()))
+            .count()
+    }
+
+    pub fn gaps(&self) -> Vec<(usize, usize)> {
+        self.empty_seats()
+            .into_iter()
+            .filter(|(_, _, kind)| *kind == EmptySeatKind::Gap)
+            .map(|(row, column, _)| (row, column))
+            .collect()
+    }
+
+    pub fn duplicates(&self) -> Vec<(usize, usize, Vec<usize>)> {
+        let mut result = vec![];
+        for (row, seats) in self.seats.iter().enumerate() {
+            for (column, passes) in seats.iter().enumerate() {
+                if passes.len() > 1 {
+                    result.push((row, column, passes.clone()));
+                }
+            }
+        }
+        result
+    }
+}
+
+impl fmt::Display for SeatMap {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (row, seats) in self.seats.iter().enumerate() {
+            write!(f, "{:3} ", row)?;
+            for (column, passes) in seats.iter().enumerate() {
+                let symbol = match passes.len() {
+                    _ if !self.layout.has_seat(row, column) => 'x',
+                    0 => '.',
+                    1 => '#',
+                    _ => '!',
+                };
+                write!(f, "{}", symbol)?;
+                if self.layout.aisles.contains(&column) {
+                    write!(f, " ")?;
+                }
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+pub fn puzzle2(input: Vec<String>) -> usize {
+    let seat_map = SeatMap::new(&BspDecoder::default().parse_all(&input).unwrap());
+    match seat_map.gaps()[..] {
+        [(row, column), ..] => seat_map.seat_id(row, column),
+        [] => panic!("No ID found"),
+    }
 }
 
 #[cfg(test)]
@@ -203,6 +581,218 @@ mod tests {
         );
     }
 
+    #[test]
+    fn bsp_decoder_default() {
+        let decoder = BspDecoder::default();
+        assert_eq!(decoder.decode("FBFBBFFRLR").unwrap(), (44, 5));
+        assert_eq!(decoder.decode_seat_id("BBFFBBFRLL").unwrap(), 820);
+        assert_eq!(decoder.encode(44, 5).unwrap(), "FBFBBFFRLR");
+        assert_eq!(decoder.encode(128, 0), None);
+    }
+
+    #[test]
+    fn bsp_decoder_custom() {
+        let decoder = BspDecoder::new(4, 2)
+            .unwrap()
+            .with_row_characters('0', '1')
+            .with_column_characters('a', 'b')
+            .with_column_multiplier(10)
+            .unwrap();
+        assert_eq!(decoder.decode("1011ba").unwrap(), (11, 2));
+        assert_eq!(decoder.decode_seat_id("1011ba").unwrap(), 112);
+        assert_eq!(decoder.encode(11, 2).unwrap(), "1011ba");
+        assert!(decoder.decode("FBFBBFFRLR").is_err());
+        assert!(decoder.decode("1011b").is_err());
+        for row in 0..16 {
+            for column in 0..4 {
+                let encoded = decoder.encode(row, column).unwrap();
+                assert_eq!(decoder.decode(&encoded).unwrap(), (row, column));
+            }
+        }
+    }
+
+    #[test]
+    fn boarding_pass_validation() {
+        let error = |reason, position| Err(BoardingPassParseError { reason, position });
+        assert_eq!(
+            "FBFBBF".parse::<BoardingPass>().map(|_| ()),
+            error("Too few instructions", 6)
+        );
+        assert_eq!(
+            "FBFBBFFRLRL".parse::<BoardingPass>().map(|_| ()),
+            error("Too many instructions", 10)
+        );
+        assert_eq!(
+            "FBFéBFFRLR".parse::<BoardingPass>().map(|_| ()),
+            error("Unknown Row Instruction", 3)
+        );
+        assert_eq!(
+            "FBFBBFFRLB".parse::<BoardingPass>().map(|_| ()),
+            error("Unknown Column Instruction", 9)
+        );
+        assert_eq!(
+            "".parse::<BoardingPass>().map(|_| ()),
+            error("Too few instructions", 0)
+        );
+    }
+
+    #[test]
+    fn parse_all_reports_every_invalid_line() {
+        let input = ["FBFBBFFRLR", "FBF", "BFFFBBFRRR", "XBFFBBFRRR"]
+            .iter()
+            .map(|x| x.to_string())
+            .collect::<Vec<_>>();
+        let errors = BspDecoder::default().parse_all(&input).unwrap_err();
+        assert_eq!(
+            errors.iter().map(|(line, _)| *line).collect::<Vec<_>>(),
+            vec![2, 4]
+        );
+        assert_eq!(
+            errors[1].1.to_string(),
+            "Unknown Row Instruction at position 0"
+        );
+    }
+
+    #[test]
+    fn seat_map() {
+        let input = [
+            "FFFFFFBLLL",
+            "FFFFFFBLLR",
+            "FFFFFFBLRR",
+            "FFFFFFBRLL",
+            "FFFFFFBLLR",
+        ]
+        .iter()
+        .map(|x| x.to_string())
+        .collect::<Vec<_>>();
+        let seat_map = SeatMap::new(&BspDecoder::default().parse_all(&input).unwrap());
+        assert_eq!(seat_map.missing_front_rows(), 1);
+        assert_eq!(seat_map.missing_back_rows(), 126);
+        assert_eq!(seat_map.gaps(), vec![(1, 2)]);
+        assert_eq!(seat_map.duplicates(), vec![(1, 1, vec![1, 4])]);
+        assert!(seat_map.is_occupied(1, 0));
+        assert!(!seat_map.is_occupied(1, 2));
+        assert!(!seat_map.is_occupied(128, 0));
+        assert!(!seat_map.is_occupied(1, 8));
+        let empty_seats = seat_map.empty_seats();
+        assert_eq!(empty_seats.len(), 128 * 8 - 4);
+        assert_eq!(empty_seats[0], (0, 0, EmptySeatKind::Front));
+        assert_eq!(empty_seats[8], (1, 2, EmptySeatKind::Gap));
+        assert_eq!(empty_seats[9], (1, 5, EmptySeatKind::Back));
+        let rendered = seat_map.to_string();
+        assert_eq!(rendered.lines().nth(1), Some("  1 #!.##..."));
+        assert_eq!(rendered.lines().count(), 128);
+    }
+
+    #[test]
+    fn bsp_decoder_rejects_oversized_widths() {
+        assert!(BspDecoder::new(32, 31).is_ok());
+        assert_eq!(
+            BspDecoder::new(64, 0).unwrap_err(),
+            BspDecoderError("Seat IDs would not fit in a usize")
+        );
+        assert!(BspDecoder::new(3, 61).is_err());
+        assert!(BspDecoder::new(usize::MAX, 1).is_err());
+        let decoder = BspDecoder::new(40, 23).unwrap();
+        let pass = "B".repeat(40) + &"R".repeat(23);
+        assert_eq!(
+            decoder.decode(&pass).unwrap(),
+            ((1 << 40) - 1, (1 << 23) - 1)
+        );
+        assert_eq!(decoder.encode((1 << 40) - 1, (1 << 23) - 1), Some(pass));
+        assert_eq!(
+            decoder.clone().with_column_multiplier(1 << 30).unwrap_err(),
+            BspDecoderError("Seat IDs would not fit in a usize")
+        );
+        assert!(decoder.with_column_multiplier(1 << 24).is_ok());
+        assert_eq!(
+            BspDecoder::new(4, 2)
+                .unwrap()
+                .with_column_multiplier(0)
+                .unwrap_err(),
+            BspDecoderError("Column multiplier is too small")
+        );
+        assert!(BspDecoder::new(4, 2)
+            .unwrap()
+            .with_column_multiplier(3)
+            .is_err());
+    }
+
+    #[test]
+    fn seating_position_conversions() {
+        let position = "FBFBBFFRLR"
+            .parse::<BoardingPass>()
+            .unwrap()
+            .to_seating_position();
+        assert_eq!(position, SeatingPosition::new(44, 5));
+        assert_eq!(position, SeatingPosition::from_id(357));
+        assert_eq!(position.to_string(), "row 44, column 5, seat ID 357");
+        assert!(SeatingPosition::new(44, 5) < SeatingPosition::new(44, 6));
+        assert!(SeatingPosition::new(44, 7) < SeatingPosition::new(45, 0));
+        assert_eq!(
+            BspDecoder::new(4, 2)
+                .unwrap()
+                .with_column_multiplier(10)
+                .unwrap()
+                .position_from_seat_id(112),
+            SeatingPosition::from_id_with_multiplier(112, 10)
+        );
+    }
+
+    #[test]
+    fn bitwise_decoding_matches_interval_fold() {
+        let decoder = BspDecoder::default();
+        for row in 0..128 {
+            for column in 0..8 {
+                let boarding_pass = decoder
+                    .parse(&decoder.encode(row, column).unwrap())
+                    .unwrap();
+                assert_eq!(
+                    boarding_pass.to_seating_position_bitwise(),
+                    boarding_pass.to_seating_position()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn plane_layout() {
+        let layout = PlaneLayout::new(30, 6)
+            .with_blocked_seat(0, 0)
+            .with_aisle_after(2);
+        let decoder = layout.decoder().unwrap();
+        assert_eq!(decoder.encode(3, 4).unwrap(), "FFFBBRLL");
+        assert_eq!(
+            layout.decode("FFFBBRLL").unwrap(),
+            SeatingPosition::from_id_with_multiplier(22, 6)
+        );
+        assert_eq!(
+            layout.decode("BBBBBLLL").unwrap_err(),
+            LayoutDecodeError::RowOutOfLayout { row: 31 }
+        );
+        assert_eq!(
+            layout.decode("FFFFFRRL").unwrap_err(),
+            LayoutDecodeError::ColumnOutOfLayout { column: 6 }
+        );
+        assert_eq!(
+            layout.decode("FFFFFLLL").unwrap_err(),
+            LayoutDecodeError::SeatBlocked { row: 0, column: 0 }
+        );
+
+        let input = [
+            "FFFFFLLR", "FFFFFLRL", "FFFFFRLL", "FFFFFRLR", "FFFFBLLL", "FFFFFLLL", "BBBBBLLL",
+        ]
+        .iter()
+        .map(|x| x.to_string())
+        .collect::<Vec<_>>();
+        let seat_map = SeatMap::with_layout(layout, &decoder.parse_all(&input).unwrap());
+        assert_eq!(seat_map.gaps(), vec![(0, 3)]);
+        assert_eq!(seat_map.rejected(), &[5, 6]);
+        assert!(seat_map.duplicates().is_empty());
+        assert_eq!(seat_map.missing_back_rows(), 28);
+        assert_eq!(seat_map.to_string().lines().next(), Some("  0 x## .##"));
+    }
+
     const TEST_INPUT: &'static [&'static str] =
         &["FBFBBFFRLR", "BFFFBBFRRR", "FFFBBBFRRR", "BBFFBBFRLL"];
 }
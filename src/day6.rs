@@ -1,51 +1,375 @@
-use std::collections::{HashMap, HashSet};
+use std::{ops, str::FromStr};
 
-pub fn puzzle1(input: Vec<String>) -> usize {
-    let mut sets = vec![HashSet::new()];
-    for line in input {
-        if let Some(current_set) = sets.last_mut() {
-            if line == "" {
-                sets.push(HashSet::new());
-            } else {
-                for question in line.chars() {
-                    current_set.insert(question);
-                }
+use crate::util::group_records;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Alphabet(Vec<char>);
+
+#[derive(Debug, PartialEq)]
+pub struct AlphabetError(&'static str);
+
+impl Default for Alphabet {
+    fn default() -> Self {
+        Alphabet(('a'..='z').collect())
+    }
+}
+
+impl Alphabet {
+    pub fn new(questions: impl IntoIterator<Item = char>) -> Result<Alphabet, AlphabetError> {
+        let mut alphabet = vec![];
+        for question in questions {
+            if alphabet.contains(&question) {
+                return Err(AlphabetError("Duplicate question identifier"));
             }
+            alphabet.push(question);
         }
+        if alphabet.len() > Answers::CAPACITY {
+            return Err(AlphabetError("Too many question identifiers"));
+        }
+        Ok(Alphabet(alphabet))
+    }
+
+    pub fn questions(&self) -> &[char] {
+        &self.0
+    }
+
+    pub fn index_of(&self, question: char) -> Option<usize> {
+        self.0.iter().position(|&x| x == question)
+    }
+
+    pub fn all_answers(&self) -> Answers {
+        (0..self.0.len()).fold(Answers::default(), |acc, i| acc | Answers(1 << i))
+    }
+
+    pub fn questions_in(&self, answers: Answers) -> impl Iterator<Item = char> + '_ {
+        self.0
+            .iter()
+            .enumerate()
+            .filter(move |(i, _)| answers.contains(*i))
+            .map(|(_, &x)| x)
+    }
+
+    pub fn parse_answers(&self, s: &str) -> Result<Answers, AnswersParseError> {
+        match self.invalid_characters(s).into_iter().next() {
+            Some(error) => Err(error),
+            None => Ok(s
+                .chars()
+                .filter_map(|x| self.index_of(x))
+                .fold(Answers::default(), |acc, i| acc | Answers(1 << i))),
+        }
+    }
+
+    pub fn invalid_characters(&self, s: &str) -> Vec<AnswersParseError> {
+        s.chars()
+            .enumerate()
+            .filter(|(_, x)| self.index_of(*x).is_none())
+            .map(|(position, character)| AnswersParseError {
+                character,
+                position,
+            })
+            .collect()
     }
-    sets.iter().map(|x| x.len()).sum()
 }
 
-pub fn puzzle2(input: Vec<String>) -> usize {
-    let mut all_answers = vec![HashSet::new()];
-    let mut people_in_group = 0;
-    let mut current_group_answers = HashMap::new();
-    for (i, line) in input.iter().enumerate() {
-        if let Some(current_group_valid_answers) = all_answers.last_mut() {
-            if line != "" {
-                people_in_group += 1;
-                for question in line.chars() {
-                    match current_group_answers.get_mut(&question) {
-                        Some(val) => *val += 1,
-                        None => {
-                            current_group_answers.insert(question, 1);
-                        }
-                    }
-                }
-            };
-            if (line == "") | (i == input.len() - 1) {
-                for (&question, &number_of_people_with_answer) in current_group_answers.iter() {
-                    if number_of_people_with_answer == people_in_group {
-                        current_group_valid_answers.insert(question);
-                    }
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Answers(u128);
+
+impl Answers {
+    pub const CAPACITY: usize = 128;
+
+    pub fn all() -> Answers {
+        Answers(u128::MAX)
+    }
+
+    pub fn contains(&self, index: usize) -> bool {
+        index < Answers::CAPACITY && self.0 >> index & 1 == 1
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.count_ones() as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    pub fn indices(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..Answers::CAPACITY).filter(move |&i| self.contains(i))
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct AnswersParseError {
+    pub character: char,
+    pub position: usize,
+}
+
+impl FromStr for Answers {
+    type Err = AnswersParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Alphabet::default().parse_answers(s)
+    }
+}
+
+impl ops::BitOr for Answers {
+    type Output = Answers;
+
+    fn bitor(self, rhs: Self) -> Self::Output {
+        Answers(self.0 | rhs.0)
+    }
+}
+
+impl ops::BitAnd for Answers {
+    type Output = Answers;
+
+    fn bitand(self, rhs: Self) -> Self::Output {
+        Answers(self.0 & rhs.0)
+    }
+}
+
+impl ops::BitXor for Answers {
+    type Output = Answers;
+
+    fn bitxor(self, rhs: Self) -> Self::Output {
+        Answers(self.0 ^ rhs.0)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Group {
+    people: Vec<Answers>,
+    questions: Answers,
+}
+
+impl Default for Group {
+    fn default() -> Self {
+        Group::new(vec![], &Alphabet::default())
+    }
+}
+
+impl Group {
+    pub fn new(people: Vec<Answers>, alphabet: &Alphabet) -> Group {
+        Group {
+            people,
+            questions: alphabet.all_answers(),
+        }
+    }
+
+    pub fn people(&self) -> &[Answers] {
+        &self.people
+    }
+
+    pub fn union(&self) -> Answers {
+        self.people
+            .iter()
+            .fold(Answers::default(), |acc, &x| acc | x)
+    }
+
+    pub fn intersection(&self) -> Answers {
+        if self.people.is_empty() {
+            return Answers::default();
+        }
+        self.people.iter().fold(self.questions, |acc, &x| acc & x)
+    }
+
+    pub fn symmetric_difference(&self) -> Answers {
+        self.people
+            .iter()
+            .fold(Answers::default(), |acc, &x| acc ^ x)
+    }
+
+    pub fn answered_by_at_least(&self, people: usize) -> Answers {
+        let mut counts = [0; Answers::CAPACITY];
+        for answers in self.people.iter() {
+            for i in answers.indices() {
+                counts[i] += 1;
+            }
+        }
+        Answers(
+            counts
+                .iter()
+                .enumerate()
+                .filter(|(_, &count)| count >= people)
+                .fold(0, |acc, (i, _)| acc | 1 << i),
+        ) & self.questions
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct InvalidAnswers {
+    pub group: usize,
+    pub person: usize,
+    pub error: AnswersParseError,
+}
+
+pub fn parse_groups(input: Vec<String>) -> Result<Vec<Group>, Vec<InvalidAnswers>> {
+    parse_groups_with_alphabet(input, &Alphabet::default())
+}
+
+pub fn parse_groups_with_alphabet(
+    input: Vec<String>,
+    alphabet: &Alphabet,
+) -> Result<Vec<Group>, Vec<InvalidAnswers>> {
+    let mut groups = vec![];
+    let mut invalid_answers = vec![];
+    for (group, lines) in group_records(&input).iter().enumerate() {
+        let mut people = vec![];
+        for (person, line) in lines.iter().enumerate() {
+            match alphabet.parse_answers(line) {
+                Ok(answers) => people.push(answers),
+                Err(_) => {
+                    invalid_answers.extend(alphabet.invalid_characters(line).into_iter().map(
+                        |error| InvalidAnswers {
+                            group,
+                            person,
+                            error,
+                        },
+                    ))
                 }
-                all_answers.push(HashSet::new());
-                people_in_group = 0;
-                current_group_answers.clear();
-            };
+            }
         }
+        groups.push(Group::new(people, alphabet));
     }
-    all_answers.iter().map(|x| x.len()).sum()
+    if invalid_answers.is_empty() {
+        Ok(groups)
+    } else {
+        Err(invalid_answers)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct QuestionStatistics {
+    pub question: char,
+    pub groups: usize,
+    pub people: usize,
+    pub unanimous_groups: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GroupStatistics {
+    pub people: usize,
+    pub answered_by_anyone: usize,
+    pub answered_by_everyone: usize,
+}
+
+#[derive(Debug)]
+pub struct SurveyReport {
+    pub questions: Vec<QuestionStatistics>,
+    pub groups: Vec<GroupStatistics>,
+    pub largest_groups: Vec<usize>,
+    pub smallest_groups: Vec<usize>,
+    pub groups_without_agreement: Vec<usize>,
+}
+
+impl SurveyReport {
+    pub fn new(groups: &[Group]) -> SurveyReport {
+        SurveyReport::with_alphabet(groups, &Alphabet::default())
+    }
+
+    pub fn with_alphabet(groups: &[Group], alphabet: &Alphabet) -> SurveyReport {
+        let questions = alphabet
+            .questions()
+            .iter()
+            .enumerate()
+            .map(|(i, &question)| QuestionStatistics {
+                question,
+                groups: groups.iter().filter(|x| x.union().contains(i)).count(),
+                people: groups
+                    .iter()
+                    .flat_map(|x| x.people())
+                    .filter(|x| x.contains(i))
+                    .count(),
+                unanimous_groups: groups
+                    .iter()
+                    .filter(|x| x.intersection().contains(i))
+                    .count(),
+            })
+            .collect();
+        let group_statistics = groups
+            .iter()
+            .map(|x| GroupStatistics {
+                people: x.people().len(),
+                answered_by_anyone: x.union().len(),
+                answered_by_everyone: x.intersection().len(),
+            })
+            .collect::<Vec<_>>();
+        let groups_with_size = |size: Option<usize>| {
+            group_statistics
+                .iter()
+                .enumerate()
+                .filter(|(_, x)| Some(x.people) == size)
+                .map(|(i, _)| i)
+                .collect()
+        };
+        SurveyReport {
+            questions,
+            largest_groups: groups_with_size(group_statistics.iter().map(|x| x.people).max()),
+            smallest_groups: groups_with_size(group_statistics.iter().map(|x| x.people).min()),
+            groups_without_agreement: group_statistics
+                .iter()
+                .enumerate()
+                .filter(|(_, x)| x.answered_by_everyone == 0)
+                .map(|(i, _)| i)
+                .collect(),
+            groups: group_statistics,
+        }
+    }
+
+    pub fn to_table(&self) -> String {
+        let mut table = format!(
+            "{:<8} | {:>6} | {:>6} | {:>9}\n",
+            "question", "groups", "people", "unanimous"
+        );
+        table.push_str(&format!(
+            "{:-<8}-+-{:->6}-+-{:->6}-+-{:->9}\n",
+            "", "", "", ""
+        ));
+        for x in self.questions.iter() {
+            table.push_str(&format!(
+                "{:<8} | {:>6} | {:>6} | {:>9}\n",
+                x.question, x.groups, x.people, x.unanimous_groups
+            ));
+        }
+        table
+    }
+
+    pub fn to_csv(&self) -> String {
+        fn quote(question: char) -> String {
+            match question {
+                ',' | '\n' | '\r' => format!("\"{}\"", question),
+                '"' => String::from("\"\"\"\""),
+                _ => question.to_string(),
+            }
+        }
+        let mut csv = String::from("question,groups,people,unanimous_groups\n");
+        for x in self.questions.iter() {
+            csv.push_str(&format!(
+                "{},{},{},{}\n",
+                quote(x.question),
+                x.groups,
+                x.people,
+                x.unanimous_groups
+            ));
+        }
+        csv
+    }
+}
+
+pub fn puzzle1(input: Vec<String>) -> usize {
+    parse_groups(input)
+        .unwrap()
+        .iter()
+        .map(|x| x.union().len())
+        .sum()
+}
+
+pub fn puzzle2(input: Vec<String>) -> usize {
+    parse_groups(input)
+        .unwrap()
+        .iter()
+        .map(|x| x.intersection().len())
+        .sum()
 }
 
 #[cfg(test)]
@@ -84,6 +408,138 @@ mod tests {
         );
     }
 
+    #[test]
+    fn group_set_algebra() {
+        let group = Group::new(
+            vec![
+                "abc".parse().unwrap(),
+                "bcd".parse().unwrap(),
+                "cx".parse().unwrap(),
+            ],
+            &Alphabet::default(),
+        );
+        let questions = |answers: Answers| {
+            Alphabet::default()
+                .questions_in(answers)
+                .collect::<String>()
+        };
+        assert_eq!(questions(group.union()), "abcdx");
+        assert_eq!(questions(group.intersection()), "c");
+        assert_eq!(questions(group.symmetric_difference()), "acdx");
+        assert_eq!(questions(group.answered_by_at_least(2)), "bc");
+        assert_eq!(group.answered_by_at_least(0).len(), 26);
+        assert_eq!(
+            group.answered_by_at_least(0),
+            Alphabet::default().all_answers()
+        );
+        assert!(Group::default().intersection().is_empty());
+        assert!("ab c".parse::<Answers>().is_err());
+    }
+
+    #[test]
+    fn parse_groups_skips_empty_groups() {
+        let input = ["", "ab\r", "", "\r", "a ", ""]
+            .iter()
+            .map(|x| x.to_string())
+            .collect();
+        let groups = parse_groups(input).unwrap();
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups[1].people().len(), 1);
+    }
+
+    #[test]
+    fn survey_report_test_input() {
+        let report = SurveyReport::new(
+            &parse_groups(TEST_INPUT.iter().map(|x| x.to_string()).collect()).unwrap(),
+        );
+        assert_eq!(
+            report.questions[0],
+            QuestionStatistics {
+                question: 'a',
+                groups: 4,
+                people: 8,
+                unanimous_groups: 3
+            }
+        );
+        assert_eq!(report.groups[1].answered_by_anyone, 3);
+        assert_eq!(report.largest_groups, vec![3]);
+        assert_eq!(report.smallest_groups, vec![0, 4]);
+        assert_eq!(report.groups_without_agreement, vec![1]);
+        assert_eq!(report.to_csv().lines().nth(2), Some("b,4,4,2"));
+        assert_eq!(
+            report.to_table().lines().nth(2),
+            Some("a        |      4 |      8 |         3")
+        );
+        assert_eq!(report.to_table().lines().count(), 28);
+    }
+
+    #[test]
+    fn invalid_characters_are_reported() {
+        let input = ["ab", "a c", "", "aB"]
+            .iter()
+            .map(|x| x.to_string())
+            .collect();
+        assert_eq!(
+            parse_groups(input).unwrap_err(),
+            vec![
+                InvalidAnswers {
+                    group: 0,
+                    person: 1,
+                    error: AnswersParseError {
+                        character: ' ',
+                        position: 1
+                    }
+                },
+                InvalidAnswers {
+                    group: 1,
+                    person: 0,
+                    error: AnswersParseError {
+                        character: 'B',
+                        position: 1
+                    }
+                }
+            ]
+        );
+    }
+
+    #[test]
+    fn unicode_alphabet() {
+        let alphabet = Alphabet::new("αβγABC".chars()).unwrap();
+        let input = ["αβA", "βγA", "", "C"]
+            .iter()
+            .map(|x| x.to_string())
+            .collect();
+        let groups = parse_groups_with_alphabet(input, &alphabet).unwrap();
+        assert_eq!(
+            alphabet
+                .questions_in(groups[0].intersection())
+                .collect::<String>(),
+            "βA"
+        );
+        assert_eq!(groups[1].answered_by_at_least(0).len(), 6);
+        let report = SurveyReport::with_alphabet(&groups, &alphabet);
+        assert_eq!(report.questions.len(), 6);
+        assert_eq!(report.to_csv().lines().nth(2), Some("β,1,2,1"));
+        assert!(Alphabet::new("aa".chars()).is_err());
+        assert!(Alphabet::new((0..129).map(|x| std::char::from_u32(0x4e00 + x).unwrap())).is_err());
+    }
+
+    #[test]
+    fn csv_quotes_special_questions() {
+        let alphabet = Alphabet::new(",\"a\n".chars()).unwrap();
+        let input = [",a", "\","].iter().map(|x| x.to_string()).collect();
+        let groups = parse_groups_with_alphabet(input, &alphabet).unwrap();
+        let csv = SurveyReport::with_alphabet(&groups, &alphabet).to_csv();
+        assert_eq!(
+            csv,
+            "question,groups,people,unanimous_groups\n\
+             \",\",1,2,1\n\
+             \"\"\"\",1,1,0\n\
+             a,1,1,0\n\
+             \"\n\",0,0,0\n"
+        );
+    }
+
     const TEST_INPUT: &'static [&'static str] = &[
         "abc", "", "a", "b", "c", "", "ab", "ac", "", "a", "a", "a", "a", "", "b",
     ];
@@ -1,130 +1,749 @@
 use std::{
-    collections::{HashMap, HashSet},
-    str::FromStr,
+    collections::{BTreeMap, HashMap, HashSet, VecDeque},
+    fmt,
+    sync::OnceLock,
 };
 
 use regex::Regex;
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct BagId(u32);
+impl BagId {
+    fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+#[derive(Debug, Default)]
+pub struct SymbolTable {
+    names: Vec<BagType>,
+    ids: HashMap<BagType, BagId>,
+}
+impl SymbolTable {
+    pub fn intern(&mut self, bag_type: &str) -> BagId {
+        if let Some(&id) = self.ids.get(bag_type) {
+            return id;
+        }
+        let id = BagId(self.names.len() as u32);
+        self.names.push(bag_type.to_string());
+        self.ids.insert(bag_type.to_string(), id);
+        id
+    }
+    pub fn get(&self, bag_type: &str) -> Option<BagId> {
+        self.ids.get(bag_type).copied()
+    }
+    pub fn name(&self, id: BagId) -> &BagType {
+        &self.names[id.index()]
+    }
+    pub fn adjective(&self, id: BagId) -> &str {
+        let name = self.name(id);
+        name.split_once(' ')
+            .map_or(name.as_str(), |(adjective, _)| adjective)
+    }
+    pub fn colour(&self, id: BagId) -> &str {
+        let name = self.name(id);
+        name.split_once(' ').map_or("", |(_, colour)| colour)
+    }
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+}
+
+struct RuleParser {
+    rule: Regex,
+    definition: Regex,
+}
+struct ParsedRule<'a> {
+    bag_type: &'a str,
+    contents: Vec<ParsedDefinition<'a>>,
+}
+struct ParsedDefinition<'a> {
+    amount: usize,
+    bag_type: &'a str,
+    text: &'a str,
+    plural: bool,
+}
+impl RuleParser {
+    fn get() -> &'static RuleParser {
+        static PARSER: OnceLock<RuleParser> = OnceLock::new();
+        PARSER.get_or_init(|| RuleParser {
+            rule: Regex::new(r"^(.+) bags? contain (.+)$").unwrap(),
+            definition: Regex::new(r"^(\d+) (.+) (bags?)$").unwrap(),
+        })
+    }
+    fn parse<'a>(&self, line: &'a str) -> Result<ParsedRule<'a>, RuleParsingError> {
+        let captures = self
+            .rule
+            .captures(line)
+            .ok_or(RuleParsingError("Rule does not match regex"))?;
+        let (bag_type, contents) = match (captures.get(1), captures.get(2)) {
+            (Some(bag_type), Some(contents)) => (bag_type.as_str(), contents.as_str()),
+            _ => return Err(RuleParsingError("Wrong number of captures")),
+        };
+        if contents == "no other bags." {
+            return Ok(ParsedRule {
+                bag_type,
+                contents: vec![],
+            });
+        }
+        let contents = contents
+            .trim_end_matches('.')
+            .split(',')
+            .map(|x| {
+                let text = x.trim();
+                let captures = self.definition.captures(text).ok_or(RuleParsingError(
+                    "Containment definition did not match regex",
+                ))?;
+                let amount = captures[1]
+                    .parse()
+                    .or(Err(RuleParsingError("Amount did not parse as number")))?;
+                Ok(ParsedDefinition {
+                    amount,
+                    bag_type: captures.get(2).unwrap().as_str(),
+                    text,
+                    plural: &captures[3] == "bags",
+                })
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(ParsedRule { bag_type, contents })
+    }
+}
+
 #[derive(Debug)]
-struct Rules(HashMap<BagType, ContainmentRules>);
+pub struct Rules {
+    symbols: SymbolTable,
+    contents: Vec<Option<Vec<ContainmentDefinition>>>,
+    containers: Vec<Vec<BagId>>,
+}
 #[derive(Debug)]
-struct RuleParsingError(&'static str);
+pub struct RuleParsingError(&'static str);
 impl Rules {
-    fn from_strings(strings: Vec<String>) -> Result<Rules, RuleParsingError> {
-        let re = Regex::new(r"^(.+) bags? contain (.+)$").unwrap();
-        let mut rules_map = HashMap::new();
+    pub fn from_strings(strings: Vec<String>) -> Result<Rules, RuleParsingError> {
+        let parser = RuleParser::get();
+        let mut symbols = SymbolTable::default();
+        let mut contents: Vec<Option<Vec<ContainmentDefinition>>> = vec![];
         for line in strings {
-            let maybe_captures = re.captures(line.as_str());
-            if let Some(captures) = maybe_captures {
-                if let (Some(bag_type), Some(containment_rules)) =
-                    (captures.get(1), captures.get(2))
-                {
-                    rules_map.insert(
-                        bag_type.as_str().to_string(),
-                        containment_rules.as_str().parse()?,
-                    );
-                } else {
-                    return Err(RuleParsingError("Wrong number of captures"));
+            let rule = parser.parse(&line)?;
+            let id = symbols.intern(rule.bag_type);
+            let definitions = rule
+                .contents
+                .iter()
+                .map(|x| ContainmentDefinition {
+                    amount: x.amount,
+                    bag: symbols.intern(x.bag_type),
+                })
+                .collect();
+            contents.resize(symbols.len(), None);
+            contents[id.index()] = Some(definitions);
+        }
+        contents.resize(symbols.len(), None);
+        Ok(Rules::from_contents(symbols, contents))
+    }
+    fn from_contents(
+        symbols: SymbolTable,
+        contents: Vec<Option<Vec<ContainmentDefinition>>>,
+    ) -> Rules {
+        let mut containers = vec![vec![]; symbols.len()];
+        for (container, definitions) in contents.iter().enumerate() {
+            for definition in definitions.iter().flatten() {
+                containers[definition.bag.index()].push(BagId(container as u32));
+            }
+        }
+        Rules {
+            symbols,
+            contents,
+            containers,
+        }
+    }
+    pub fn lint(strings: &[String]) -> Vec<Lint> {
+        let parser = RuleParser::get();
+        let mut lints = vec![];
+        let mut definitions: BTreeMap<BagType, Vec<usize>> = BTreeMap::new();
+        let mut references: BTreeMap<BagType, Vec<usize>> = BTreeMap::new();
+        for (line, string) in strings.iter().enumerate() {
+            let rule = match parser.parse(string) {
+                Ok(rule) => rule,
+                Err(RuleParsingError(reason)) => {
+                    lints.push(Lint::Unparseable { line, reason });
+                    continue;
                 }
-            } else {
-                return Err(RuleParsingError("Rule does not match regex"));
+            };
+            for definition in rule.contents.iter() {
+                if definition.bag_type == rule.bag_type {
+                    lints.push(Lint::SelfContainment {
+                        bag_type: rule.bag_type.to_string(),
+                        line,
+                    });
+                }
+                if (definition.amount == 1) == definition.plural {
+                    lints.push(Lint::PluralMismatch {
+                        line,
+                        text: definition.text.to_string(),
+                    });
+                }
+                if definition.bag_type == rule.bag_type {
+                    continue;
+                }
+                references
+                    .entry(definition.bag_type.to_string())
+                    .or_default()
+                    .push(line);
+            }
+            definitions
+                .entry(rule.bag_type.to_string())
+                .or_default()
+                .push(line);
+        }
+        for (bag_type, lines) in definitions.iter() {
+            if lines.len() > 1 {
+                lints.push(Lint::DuplicateRule {
+                    bag_type: bag_type.clone(),
+                    lines: lines.clone(),
+                });
+            }
+            if !references.contains_key(bag_type) {
+                lints.push(Lint::UnreferencedBag {
+                    bag_type: bag_type.clone(),
+                    line: lines[0],
+                });
+            }
+        }
+        for (bag_type, lines) in references {
+            if !definitions.contains_key(&bag_type) {
+                lints.push(Lint::UndefinedBag {
+                    bag_type,
+                    referenced_on: lines,
+                });
             }
         }
-        Ok(Rules(rules_map))
+        lints
     }
-    fn get_bags_which_can_contain(&self, bag_type: &BagType) -> HashSet<&BagType> {
+    pub fn symbols(&self) -> &SymbolTable {
+        &self.symbols
+    }
+    pub fn contents(&self, id: BagId) -> Option<&[ContainmentDefinition]> {
+        self.contents.get(id.index())?.as_deref()
+    }
+    fn definitions_of(&self, id: BagId) -> &[ContainmentDefinition] {
+        self.contents(id).unwrap_or(&[])
+    }
+    pub fn is_defined(&self, bag_type: &str) -> bool {
+        self.resolve(bag_type).is_ok()
+    }
+    fn resolve(&self, bag_type: &str) -> Result<BagId, RuleQueryError> {
+        match self.symbols.get(bag_type) {
+            Some(id) if self.contents(id).is_some() => Ok(id),
+            _ => Err(RuleQueryError::UndefinedBag(bag_type.to_string())),
+        }
+    }
+    fn names(&self, ids: impl IntoIterator<Item = BagId>) -> Vec<&BagType> {
+        ids.into_iter().map(|x| self.symbols.name(x)).collect()
+    }
+    pub fn ancestors_of(&self, bag_type: &str) -> Result<HashSet<&BagType>, RuleQueryError> {
+        let id = self.resolve(bag_type)?;
+        Ok(self.names(self.ancestors(id)).into_iter().collect())
+    }
+    pub fn total_contents_of(&self, bag_type: &str) -> Result<usize, RuleQueryError> {
+        self.total_contents(self.resolve(bag_type)?)
+    }
+    pub fn ancestors(&self, id: BagId) -> HashSet<BagId> {
         let mut result = HashSet::new();
-        for (potential_container, rules) in self.0.iter() {
-            if let ContainmentRules::Contains(defs) = rules {
-                if defs.iter().any(|x| &x.bag_type == bag_type) {
-                    result.insert(potential_container);
-                    for second_order_result in
-                        self.get_bags_which_can_contain(potential_container).iter()
-                    {
-                        result.insert(second_order_result);
-                    }
-                };
-            };
+        let mut queue = vec![id];
+        while let Some(current) = queue.pop() {
+            for &container in self.containers[current.index()].iter() {
+                if result.insert(container) {
+                    queue.push(container);
+                }
+            }
         }
         result
     }
-    fn get_number_of_bags_contained_in(&self, bag_type: &BagType) -> usize {
-        match self.0.get(bag_type) {
-            None => 0,
-            Some(ContainmentRules::ContainsNothing) => 0,
-            Some(ContainmentRules::Contains(definitions)) => {
-                definitions.iter().fold(0, |acc, item| {
-                    acc + item.amount * (self.get_number_of_bags_contained_in(&item.bag_type) + 1)
+    pub fn descendants(&self, id: BagId) -> HashSet<BagId> {
+        let mut result = HashSet::new();
+        let mut queue = vec![id];
+        while let Some(current) = queue.pop() {
+            for definition in self.definitions_of(current) {
+                if result.insert(definition.bag) {
+                    queue.push(definition.bag);
+                }
+            }
+        }
+        result
+    }
+    fn subgraph_edges(&self, subgraph: Subgraph) -> Result<Edges<'_>, RuleQueryError> {
+        let included: Option<HashSet<BagId>> = match subgraph {
+            Subgraph::All => None,
+            Subgraph::ReachableFrom(bag_type) => {
+                let id = self.resolve(bag_type)?;
+                let mut result = self.descendants(id);
+                result.insert(id);
+                Some(result)
+            }
+            Subgraph::LeadingTo(bag_type) => {
+                let id = self.resolve(bag_type)?;
+                let mut result = self.ancestors(id);
+                result.insert(id);
+                Some(result)
+            }
+        };
+        let is_included = |x: BagId| included.as_ref().is_none_or(|y| y.contains(&x));
+        Ok(self
+            .contents
+            .iter()
+            .enumerate()
+            .filter_map(|(id, definitions)| Some((BagId(id as u32), definitions.as_ref()?)))
+            .filter(|(id, _)| is_included(*id))
+            .map(|(id, definitions)| {
+                let mut edges = definitions
+                    .iter()
+                    .filter(|x| is_included(x.bag))
+                    .map(|x| (self.symbols.name(x.bag), x.amount))
+                    .collect::<Vec<_>>();
+                edges.sort();
+                (self.symbols.name(id), edges)
+            })
+            .collect())
+    }
+    pub fn to_dot(&self, subgraph: Subgraph) -> Result<String, RuleQueryError> {
+        let quote = |x: &str| format!("\"{}\"", x.replace('\\', "\\\\").replace('"', "\\\""));
+        let mut dot = String::from("digraph bags {\n");
+        for (bag_type, edges) in self.subgraph_edges(subgraph)? {
+            dot.push_str(&format!("    {};\n", quote(bag_type)));
+            for (contained, amount) in edges {
+                dot.push_str(&format!(
+                    "    {} -> {} [label=\"{}\"];\n",
+                    quote(bag_type),
+                    quote(contained),
+                    amount
+                ));
+            }
+        }
+        dot.push_str("}\n");
+        Ok(dot)
+    }
+    pub fn to_json(&self, subgraph: Subgraph) -> Result<String, RuleQueryError> {
+        fn quote(s: &str) -> String {
+            let mut result = String::from("\"");
+            for c in s.chars() {
+                match c {
+                    '"' => result.push_str("\\\""),
+                    '\\' => result.push_str("\\\\"),
+                    c if (c as u32) < 0x20 => result.push_str(&format!("\\u{:04x}", c as u32)),
+                    c => result.push(c),
+                }
+            }
+            result.push('"');
+            result
+        }
+        let bags = self
+            .subgraph_edges(subgraph)?
+            .into_iter()
+            .map(|(bag_type, edges)| {
+                let contents = edges
+                    .iter()
+                    .map(|(contained, amount)| format!("{}:{}", quote(contained), amount))
+                    .collect::<Vec<_>>();
+                format!("{}:{{{}}}", quote(bag_type), contents.join(","))
+            })
+            .collect::<Vec<_>>();
+        Ok(format!("{{{}}}", bags.join(",")))
+    }
+    fn post_order(&self, id: BagId) -> Result<Vec<BagId>, RuleQueryError> {
+        let mut order = vec![];
+        let mut visited = vec![false; self.symbols.len()];
+        let mut on_path = vec![false; self.symbols.len()];
+        let mut path = vec![];
+        let mut stack = vec![(id, false)];
+        while let Some((current, expanded)) = stack.pop() {
+            if expanded {
+                order.push(current);
+                path.pop();
+                on_path[current.index()] = false;
+            } else if !visited[current.index()] {
+                visited[current.index()] = true;
+                stack.push((current, true));
+                path.push(current);
+                on_path[current.index()] = true;
+                for definition in self.definitions_of(current) {
+                    if on_path[definition.bag.index()] {
+                        let start = path.iter().position(|&x| x == definition.bag).unwrap_or(0);
+                        let mut cycle: Vec<BagType> = self
+                            .names(path[start..].iter().copied())
+                            .into_iter()
+                            .cloned()
+                            .collect();
+                        cycle.push(self.symbols.name(definition.bag).clone());
+                        return Err(RuleQueryError::Cycle(cycle));
+                    }
+                    if !visited[definition.bag.index()] {
+                        stack.push((definition.bag, false));
+                    }
+                }
+            }
+        }
+        Ok(order)
+    }
+    fn accumulate(
+        &self,
+        id: BagId,
+        f: impl Fn(&ContainmentDefinition, usize) -> Option<usize>,
+    ) -> Result<usize, RuleQueryError> {
+        let mut values = vec![0; self.symbols.len()];
+        for current in self.post_order(id)? {
+            values[current.index()] = self
+                .definitions_of(current)
+                .iter()
+                .try_fold(0usize, |sum, x| {
+                    sum.checked_add(f(x, values[x.bag.index()])?)
                 })
+                .ok_or_else(|| RuleQueryError::Overflow(self.symbols.name(current).clone()))?;
+        }
+        Ok(values[id.index()])
+    }
+    pub fn total_contents(&self, id: BagId) -> Result<usize, RuleQueryError> {
+        self.accumulate(id, |x, total| x.amount.checked_mul(total.checked_add(1)?))
+    }
+    pub fn multiplicity_of(&self, outer: &str, inner: &str) -> Result<usize, RuleQueryError> {
+        let outer = self.resolve(outer)?;
+        match self.symbols.get(inner) {
+            Some(inner) => self.accumulate(outer, |x, multiplicity| {
+                x.amount
+                    .checked_mul(multiplicity.checked_add((x.bag == inner) as usize)?)
+            }),
+            None => self.post_order(outer).map(|_| 0),
+        }
+    }
+    pub fn max_depth(&self, bag_type: &str) -> Result<usize, RuleQueryError> {
+        let id = self.resolve(bag_type)?;
+        let mut depths = vec![0; self.symbols.len()];
+        for current in self.post_order(id)? {
+            depths[current.index()] = self
+                .definitions_of(current)
+                .iter()
+                .map(|x| depths[x.bag.index()] + 1)
+                .max()
+                .unwrap_or(0);
+        }
+        Ok(depths[id.index()])
+    }
+    pub fn containment_tree(&self, bag_type: &str) -> Result<ContainmentTree, RuleQueryError> {
+        let id = self.resolve(bag_type)?;
+        self.post_order(id)?;
+        let node = |id: BagId, amount, total| ContainmentTree {
+            bag_type: self.symbols.name(id).clone(),
+            amount,
+            total,
+            children: vec![],
+        };
+        let mut stack = vec![(node(id, 1, 1), id, 0)];
+        loop {
+            let (tree, current, next) = stack.last_mut().unwrap();
+            if let Some(definition) = self.definitions_of(*current).get(*next) {
+                *next += 1;
+                let total = tree.total.checked_mul(definition.amount).ok_or_else(|| {
+                    RuleQueryError::Overflow(self.symbols.name(definition.bag).clone())
+                })?;
+                let child = node(definition.bag, definition.amount, total);
+                stack.push((child, definition.bag, 0));
+            } else {
+                let (tree, _, _) = stack.pop().unwrap();
+                match stack.last_mut() {
+                    Some((parent, _, _)) => parent.children.push(tree),
+                    None => return Ok(tree),
+                }
             }
         }
     }
-}
-type BagType = String;
-#[derive(Debug)]
-enum ContainmentRules {
-    ContainsNothing,
-    Contains(Vec<ContainmentDefinition>),
-}
-impl FromStr for ContainmentRules {
-    type Err = RuleParsingError;
-
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        if s == "no other bags." {
-            Ok(ContainmentRules::ContainsNothing)
-        } else {
-            Ok(ContainmentRules::Contains(
-                s.split(',')
-                    .map(|rule_string| rule_string.parse())
-                    .collect::<Result<_, _>>()?,
-            ))
+    pub fn shortest_path(
+        &self,
+        outer: &str,
+        inner: &str,
+    ) -> Result<Option<Vec<&BagType>>, RuleQueryError> {
+        let outer = self.resolve(outer)?;
+        let inner = match self.symbols.get(inner) {
+            Some(inner) => inner,
+            None => return Ok(None),
+        };
+        let mut previous: HashMap<BagId, BagId> = HashMap::new();
+        let mut queue = VecDeque::from(vec![outer]);
+        while let Some(current) = queue.pop_front() {
+            if current == inner {
+                let mut path = vec![current];
+                while let Some(&before) = previous.get(&path[path.len() - 1]) {
+                    path.push(before);
+                }
+                path.reverse();
+                return Ok(Some(self.names(path)));
+            }
+            for definition in self.definitions_of(current) {
+                if definition.bag != outer && !previous.contains_key(&definition.bag) {
+                    previous.insert(definition.bag, current);
+                    queue.push_back(definition.bag);
+                }
+            }
         }
+        Ok(None)
+    }
+    pub fn all_paths(
+        &self,
+        outer: &str,
+        inner: &str,
+    ) -> Result<Vec<Vec<&BagType>>, RuleQueryError> {
+        let outer = self.resolve(outer)?;
+        let inner = match self.symbols.get(inner) {
+            Some(inner) => inner,
+            None => return Ok(vec![]),
+        };
+        let mut result = vec![];
+        let mut on_path = vec![false; self.symbols.len()];
+        let mut path = vec![outer];
+        let mut next = vec![0];
+        on_path[outer.index()] = true;
+        while let Some(&current) = path.last() {
+            let index = next[next.len() - 1];
+            let definitions = self.definitions_of(current);
+            if current == inner {
+                result.push(self.names(path.iter().copied()));
+            } else if let Some(definition) = definitions.get(index) {
+                *next.last_mut().unwrap() += 1;
+                if !on_path[definition.bag.index()] {
+                    on_path[definition.bag.index()] = true;
+                    path.push(definition.bag);
+                    next.push(0);
+                }
+                continue;
+            }
+            on_path[current.index()] = false;
+            path.pop();
+            next.pop();
+        }
+        Ok(result)
     }
 }
-#[derive(Debug)]
-struct ContainmentDefinition {
-    amount: usize,
-    bag_type: BagType,
-}
-impl FromStr for ContainmentDefinition {
-    type Err = RuleParsingError;
-
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let re = Regex::new(r"(\d+) (.+) bag").unwrap();
-        let maybe_matches = re.captures(s);
-        if let Some(matches) = maybe_matches {
-            if let (Some(amount_match), Some(bag_type_match)) = (matches.get(1), matches.get(2)) {
-                if let Ok(amount) = amount_match.as_str().parse() {
-                    Ok(ContainmentDefinition {
-                        amount,
-                        bag_type: bag_type_match.as_str().to_string(),
-                    })
-                } else {
-                    Err(RuleParsingError("Amount did not parse as number"))
+impl fmt::Display for Rules {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (id, definitions) in self.contents.iter().enumerate() {
+            if let Some(definitions) = definitions {
+                write!(f, "{} bags contain ", self.symbols.name(BagId(id as u32)))?;
+                if definitions.is_empty() {
+                    write!(f, "no other bags")?;
                 }
+                for (i, definition) in definitions.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(
+                        f,
+                        "{} {} {}",
+                        definition.amount,
+                        self.symbols.name(definition.bag),
+                        if definition.amount == 1 {
+                            "bag"
+                        } else {
+                            "bags"
+                        }
+                    )?;
+                }
+                writeln!(f, ".")?;
+            }
+        }
+        Ok(())
+    }
+}
+#[derive(Debug, Clone)]
+pub struct RuleGenerator {
+    bags: usize,
+    depth: usize,
+    max_children: usize,
+    max_amount: usize,
+    seed: u64,
+}
+impl RuleGenerator {
+    const ADJECTIVES: [&'static str; 8] = [
+        "light", "dark", "bright", "muted", "shiny", "faded", "dotted", "vibrant",
+    ];
+    const COLOURS: [&'static str; 8] = [
+        "red", "orange", "white", "yellow", "gold", "olive", "plum", "blue",
+    ];
+    pub fn new(bags: usize, depth: usize) -> RuleGenerator {
+        RuleGenerator {
+            bags,
+            depth,
+            max_children: 4,
+            max_amount: 5,
+            seed: 2020,
+        }
+    }
+    pub fn with_max_children(mut self, max_children: usize) -> RuleGenerator {
+        self.max_children = max_children;
+        self
+    }
+    pub fn with_max_amount(mut self, max_amount: usize) -> RuleGenerator {
+        self.max_amount = max_amount;
+        self
+    }
+    pub fn with_seed(mut self, seed: u64) -> RuleGenerator {
+        self.seed = seed;
+        self
+    }
+    fn name(i: usize) -> String {
+        let (adjectives, colours) = (Self::ADJECTIVES.len(), Self::COLOURS.len());
+        let generation = i / (adjectives * colours);
+        format!(
+            "{}{} {}",
+            Self::ADJECTIVES[i % adjectives],
+            if generation > 0 {
+                generation.to_string()
             } else {
-                panic!("Wrong number of matches")
+                String::new()
+            },
+            Self::COLOURS[i / adjectives % colours]
+        )
+    }
+    pub fn generate(&self) -> Rules {
+        let mut state = match splitmix64(self.seed) {
+            0 => 0x9e37_79b9_7f4a_7c15,
+            state => state,
+        };
+        let mut random = move |bound: usize| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            (state % bound as u64) as usize
+        };
+        let depth = self.depth.min(self.bags.saturating_sub(1));
+        let levels = (0..self.bags)
+            .map(|i| if i <= depth { i } else { random(depth + 1) })
+            .collect::<Vec<_>>();
+        let mut by_level = (0..self.bags).collect::<Vec<_>>();
+        by_level.sort_by_key(|&i| levels[i]);
+        let mut deeper_from = vec![self.bags; depth + 1];
+        for (position, &i) in by_level.iter().enumerate().rev() {
+            if levels[i] > 0 {
+                deeper_from[levels[i] - 1] = position;
             }
-        } else {
-            Err(RuleParsingError(
-                "Containment definition did not match regex",
-            ))
         }
+        for level in (0..depth).rev() {
+            deeper_from[level] = deeper_from[level].min(deeper_from[level + 1]);
+        }
+        let mut symbols = SymbolTable::default();
+        for i in 0..self.bags {
+            symbols.intern(&RuleGenerator::name(i));
+        }
+        let contents = (0..self.bags)
+            .map(|i| {
+                let candidates = &by_level[deeper_from[levels[i]]..];
+                let mut children = vec![];
+                if i < depth {
+                    children.push(i + 1);
+                }
+                if !candidates.is_empty() && self.max_children > 0 {
+                    for _ in 0..random(self.max_children + 1) {
+                        let child = candidates[random(candidates.len())];
+                        if !children.contains(&child) && children.len() < self.max_children {
+                            children.push(child);
+                        }
+                    }
+                }
+                Some(
+                    children
+                        .into_iter()
+                        .map(|j| ContainmentDefinition {
+                            amount: random(self.max_amount.max(1)) + 1,
+                            bag: BagId(j as u32),
+                        })
+                        .collect(),
+                )
+            })
+            .collect();
+        Rules::from_contents(symbols, contents)
     }
 }
+fn splitmix64(seed: u64) -> u64 {
+    let mut z = seed.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+#[derive(Debug, Clone, PartialEq)]
+pub struct ContainmentTree {
+    pub bag_type: BagType,
+    pub amount: usize,
+    pub total: usize,
+    pub children: Vec<ContainmentTree>,
+}
+impl Drop for ContainmentTree {
+    fn drop(&mut self) {
+        let mut stack = std::mem::take(&mut self.children);
+        while let Some(mut tree) = stack.pop() {
+            stack.append(&mut tree.children);
+        }
+    }
+}
+#[derive(Debug, PartialEq)]
+pub enum Lint {
+    DuplicateRule {
+        bag_type: BagType,
+        lines: Vec<usize>,
+    },
+    UndefinedBag {
+        bag_type: BagType,
+        referenced_on: Vec<usize>,
+    },
+    UnreferencedBag {
+        bag_type: BagType,
+        line: usize,
+    },
+    PluralMismatch {
+        line: usize,
+        text: String,
+    },
+    SelfContainment {
+        bag_type: BagType,
+        line: usize,
+    },
+    Unparseable {
+        line: usize,
+        reason: &'static str,
+    },
+}
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Subgraph<'a> {
+    All,
+    ReachableFrom(&'a str),
+    LeadingTo(&'a str),
+}
+#[derive(Debug, PartialEq)]
+pub enum RuleQueryError {
+    Cycle(Vec<BagType>),
+    UndefinedBag(BagType),
+    Overflow(BagType),
+}
+pub type BagType = String;
+type Edges<'a> = BTreeMap<&'a BagType, Vec<(&'a BagType, usize)>>;
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ContainmentDefinition {
+    pub amount: usize,
+    pub bag: BagId,
+}
+
+const TARGET_BAG: &str = "shiny gold";
 
 pub fn puzzle1(input: Vec<String>) -> usize {
     Rules::from_strings(input)
         .unwrap()
-        .get_bags_which_can_contain(&"shiny gold".to_string())
-        .iter()
-        .count()
+        .ancestors_of(TARGET_BAG)
+        .unwrap()
+        .len()
 }
 
 pub fn puzzle2(input: Vec<String>) -> usize {
     Rules::from_strings(input)
         .unwrap()
-        .get_number_of_bags_contained_in(&"shiny gold".to_string())
+        .total_contents_of(TARGET_BAG)
+        .unwrap()
 }
 
 #[cfg(test)]
@@ -171,6 +790,348 @@ mod tests {
         );
     }
 
+    #[test]
+    fn queries_for_any_bag() {
+        let rules =
+            Rules::from_strings(TEST_INPUT.iter().map(|x| x.to_string()).collect()).unwrap();
+        let mut ancestors = rules
+            .ancestors_of("muted yellow")
+            .unwrap()
+            .into_iter()
+            .collect::<Vec<_>>();
+        ancestors.sort();
+        assert_eq!(ancestors, vec!["dark orange", "light red"]);
+        assert_eq!(rules.total_contents_of("dark olive").unwrap(), 7);
+        assert_eq!(rules.total_contents_of("faded blue").unwrap(), 0);
+        assert_eq!(
+            rules.total_contents_of("plaid magenta"),
+            Err(RuleQueryError::UndefinedBag("plaid magenta".to_string()))
+        );
+        assert_eq!(
+            rules.ancestors_of("plaid magenta"),
+            Err(RuleQueryError::UndefinedBag("plaid magenta".to_string()))
+        );
+    }
+
+    #[test]
+    fn export_to_dot() {
+        let rules =
+            Rules::from_strings(TEST_INPUT.iter().map(|x| x.to_string()).collect()).unwrap();
+        assert_eq!(
+            rules.to_dot(Subgraph::ReachableFrom("dark olive")).unwrap(),
+            "digraph bags {\n    \"dark olive\";\n    \"dark olive\" -> \"dotted black\" [label=\"4\"];\n    \"dark olive\" -> \"faded blue\" [label=\"3\"];\n    \"dotted black\";\n    \"faded blue\";\n}\n"
+        );
+        assert_eq!(
+            rules.to_dot(Subgraph::All).unwrap().matches(" -> ").count(),
+            13
+        );
+        assert_eq!(
+            rules.to_dot(Subgraph::LeadingTo("plaid magenta")),
+            Err(RuleQueryError::UndefinedBag("plaid magenta".to_string()))
+        );
+    }
+
+    #[test]
+    fn export_to_json() {
+        let rules =
+            Rules::from_strings(TEST_INPUT.iter().map(|x| x.to_string()).collect()).unwrap();
+        assert_eq!(
+            rules.to_json(Subgraph::LeadingTo("bright white")).unwrap(),
+            r#"{"bright white":{},"dark orange":{"bright white":3},"light red":{"bright white":1}}"#
+        );
+        assert_eq!(
+            rules
+                .to_json(Subgraph::ReachableFrom("faded blue"))
+                .unwrap(),
+            r#"{"faded blue":{}}"#
+        );
+    }
+
+    #[test]
+    fn containment_tree() {
+        let rules =
+            Rules::from_strings(TEST_INPUT.iter().map(|x| x.to_string()).collect()).unwrap();
+        let tree = rules.containment_tree("shiny gold").unwrap();
+        assert_eq!(tree.children.len(), 2);
+        let vibrant_plum = &tree.children[1];
+        assert_eq!(vibrant_plum.bag_type, "vibrant plum");
+        assert_eq!(vibrant_plum.amount, 2);
+        assert_eq!(vibrant_plum.children[1].bag_type, "dotted black");
+        assert_eq!(vibrant_plum.children[1].amount, 6);
+        assert_eq!(vibrant_plum.children[1].total, 12);
+        assert!(vibrant_plum.children[1].children.is_empty());
+    }
+
+    #[test]
+    fn path_queries() {
+        let rules =
+            Rules::from_strings(TEST_INPUT.iter().map(|x| x.to_string()).collect()).unwrap();
+        assert_eq!(
+            rules
+                .shortest_path("light red", "shiny gold")
+                .unwrap()
+                .unwrap(),
+            vec!["light red", "bright white", "shiny gold"]
+        );
+        assert_eq!(
+            rules.shortest_path("shiny gold", "light red").unwrap(),
+            None
+        );
+        let mut paths = rules.all_paths("light red", "faded blue").unwrap();
+        paths.sort();
+        assert_eq!(paths.len(), 5);
+        assert_eq!(
+            paths[0],
+            vec![
+                "light red",
+                "bright white",
+                "shiny gold",
+                "dark olive",
+                "faded blue"
+            ]
+        );
+        assert_eq!(rules.max_depth("light red").unwrap(), 4);
+        assert_eq!(rules.max_depth("faded blue").unwrap(), 0);
+        assert_eq!(
+            rules.multiplicity_of("shiny gold", "faded blue").unwrap(),
+            13
+        );
+        assert_eq!(rules.multiplicity_of("light red", "shiny gold").unwrap(), 5);
+        assert_eq!(
+            rules.multiplicity_of("faded blue", "shiny gold").unwrap(),
+            0
+        );
+    }
+
+    #[test]
+    fn lint_clean_rules() {
+        let lints = Rules::lint(&TEST_INPUT.iter().map(|x| x.to_string()).collect::<Vec<_>>());
+        assert_eq!(
+            lints,
+            vec![
+                Lint::UnreferencedBag {
+                    bag_type: "dark orange".to_string(),
+                    line: 1
+                },
+                Lint::UnreferencedBag {
+                    bag_type: "light red".to_string(),
+                    line: 0
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn lint_problematic_rules() {
+        let input = [
+            "shiny gold bags contain 1 dark red bags, 2 faded blue bag.",
+            "dark red bags contain 1 dark red bag, 3 plaid magenta bags.",
+            "faded blue bags contain no other bags.",
+            "shiny gold bags contain 1 faded blue bag.",
+            "this line is not a rule",
+            "lonely teal bags contain 1 lonely teal bag.",
+            "bright white bags contain 1 shiny gold bag, 2 faded blue bag.",
+        ]
+        .iter()
+        .map(|x| x.to_string())
+        .collect::<Vec<_>>();
+        assert_eq!(
+            Rules::lint(&input),
+            vec![
+                Lint::PluralMismatch {
+                    line: 0,
+                    text: "1 dark red bags".to_string()
+                },
+                Lint::PluralMismatch {
+                    line: 0,
+                    text: "2 faded blue bag".to_string()
+                },
+                Lint::SelfContainment {
+                    bag_type: "dark red".to_string(),
+                    line: 1
+                },
+                Lint::Unparseable {
+                    line: 4,
+                    reason: "Rule does not match regex"
+                },
+                Lint::SelfContainment {
+                    bag_type: "lonely teal".to_string(),
+                    line: 5
+                },
+                Lint::PluralMismatch {
+                    line: 6,
+                    text: "2 faded blue bag".to_string()
+                },
+                Lint::UnreferencedBag {
+                    bag_type: "bright white".to_string(),
+                    line: 6
+                },
+                Lint::UnreferencedBag {
+                    bag_type: "lonely teal".to_string(),
+                    line: 5
+                },
+                Lint::DuplicateRule {
+                    bag_type: "shiny gold".to_string(),
+                    lines: vec![0, 3]
+                },
+                Lint::UndefinedBag {
+                    bag_type: "plaid magenta".to_string(),
+                    referenced_on: vec![1]
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn display_round_trips() {
+        let input = [
+            "shiny gold bags contain 1 dark red bag, 2 faded blue bags.",
+            "dark red bags contain no other bags.",
+            "faded blue bags contain 1 dark red bag.",
+        ];
+        let rules = Rules::from_strings(input.iter().map(|x| x.to_string()).collect()).unwrap();
+        assert_eq!(rules.to_string(), input.join("\n") + "\n");
+
+        let rules =
+            Rules::from_strings(TEST_INPUT.iter().map(|x| x.to_string()).collect()).unwrap();
+        let mut lines = rules
+            .to_string()
+            .lines()
+            .map(|x| x.to_string())
+            .collect::<Vec<_>>();
+        lines.sort();
+        let mut expected = TEST_INPUT.to_vec();
+        expected.sort();
+        assert_eq!(lines, expected);
+    }
+
+    #[test]
+    fn generate_many_rules() {
+        let rules = RuleGenerator::new(200_000, 50).generate();
+        assert_eq!(rules.symbols().len(), 200_000);
+        assert_eq!(rules.max_depth(rules.symbols().name(BagId(0))).unwrap(), 50);
+    }
+
+    #[test]
+    fn deep_rule_counts_report_overflow() {
+        let rules = RuleGenerator::new(200, 50).generate();
+        assert!(matches!(
+            rules.total_contents_of("light red"),
+            Err(RuleQueryError::Overflow(_))
+        ));
+        assert!(matches!(
+            rules.containment_tree("light red"),
+            Err(RuleQueryError::Overflow(_))
+        ));
+    }
+
+    #[test]
+    fn generated_rules() {
+        let generator = RuleGenerator::new(500, 12).with_seed(7);
+        let rules = generator.generate();
+        assert_eq!(rules.symbols().len(), 500);
+        assert_eq!(rules.max_depth(rules.symbols().name(BagId(0))).unwrap(), 12);
+        for i in 0..500 {
+            let name = rules.symbols().name(BagId(i));
+            assert!(rules.max_depth(name).unwrap() <= 12);
+        }
+        let text = rules.to_string();
+        assert_eq!(text, generator.generate().to_string());
+        assert_ne!(text, generator.clone().with_seed(8).generate().to_string());
+        assert_ne!(
+            generator.clone().with_seed(2).generate().to_string(),
+            generator.clone().with_seed(3).generate().to_string()
+        );
+        let sorted_lines = |text: String| {
+            let mut lines = text.lines().map(|x| x.to_string()).collect::<Vec<_>>();
+            lines.sort();
+            lines
+        };
+        let reparsed = Rules::from_strings(sorted_lines(text.clone())).unwrap();
+        assert_eq!(
+            sorted_lines(reparsed.to_string()),
+            sorted_lines(text.clone())
+        );
+        assert!(
+            Rules::lint(&text.lines().map(|x| x.to_string()).collect::<Vec<_>>())
+                .iter()
+                .all(|x| matches!(x, Lint::UnreferencedBag { .. }))
+        );
+    }
+
+    #[test]
+    fn cyclic_rules_are_reported() {
+        let rules = Rules::from_strings(
+            [
+                "shiny gold bags contain 1 dark red bag.",
+                "dark red bags contain 2 dark orange bags, 1 faded blue bag.",
+                "dark orange bags contain 1 shiny gold bag.",
+                "faded blue bags contain no other bags.",
+            ]
+            .iter()
+            .map(|x| x.to_string())
+            .collect(),
+        )
+        .unwrap();
+        assert_eq!(
+            rules.total_contents_of("dark red"),
+            Err(RuleQueryError::Cycle(vec![
+                "dark red".to_string(),
+                "dark orange".to_string(),
+                "shiny gold".to_string(),
+                "dark red".to_string()
+            ]))
+        );
+        assert_eq!(rules.total_contents_of("faded blue").unwrap(), 0);
+        assert_eq!(rules.ancestors_of("shiny gold").unwrap().len(), 3);
+    }
+
+    #[test]
+    fn deep_rules_do_not_overflow() {
+        let mut input: Vec<String> = (0..100_000)
+            .map(|i| format!("shade{} red bags contain 1 shade{} red bag.", i, i + 1))
+            .collect();
+        input.push("shade100000 red bags contain no other bags.".to_string());
+        let rules = Rules::from_strings(input).unwrap();
+        assert_eq!(rules.total_contents_of("shade0 red").unwrap(), 100_000);
+        assert_eq!(
+            rules.ancestors_of("shade100000 red").unwrap().len(),
+            100_000
+        );
+        let mut tree = &rules.containment_tree("shade0 red").unwrap();
+        let mut depth = 0;
+        while let [child] = &tree.children[..] {
+            tree = child;
+            depth += 1;
+        }
+        assert_eq!(
+            (depth, tree.bag_type.as_str()),
+            (100_000, "shade100000 red")
+        );
+        let paths = rules.all_paths("shade0 red", "shade100000 red").unwrap();
+        assert_eq!(paths.len(), 1);
+        assert_eq!(paths[0].len(), 100_001);
+    }
+
+    #[test]
+    fn interned_bag_ids() {
+        let rules =
+            Rules::from_strings(TEST_INPUT.iter().map(|x| x.to_string()).collect()).unwrap();
+        let symbols = rules.symbols();
+        assert_eq!(symbols.len(), 9);
+        let shiny_gold = symbols.get("shiny gold").unwrap();
+        assert_eq!(symbols.name(shiny_gold), "shiny gold");
+        assert_eq!(symbols.adjective(shiny_gold), "shiny");
+        assert_eq!(symbols.colour(shiny_gold), "gold");
+        assert_eq!(rules.total_contents(shiny_gold).unwrap(), 32);
+        assert_eq!(rules.ancestors(shiny_gold).len(), 4);
+        assert_eq!(rules.descendants(shiny_gold).len(), 4);
+        let contents = rules.contents(shiny_gold).unwrap();
+        assert_eq!(symbols.name(contents[1].bag), "vibrant plum");
+        assert_eq!(contents[1].amount, 2);
+        assert_eq!(symbols.get("plaid magenta"), None);
+    }
+
     const TEST_INPUT: &'static [&'static str] = &[
         "light red bags contain 1 bright white bag, 2 muted yellow bags.",
         "dark orange bags contain 3 bright white bags, 4 muted yellow bags.",
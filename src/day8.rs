@@ -1,106 +1,938 @@
-use std::{collections::BTreeSet, convert::TryFrom, str::FromStr};
-#[derive(Debug, PartialEq)]
-enum Command {
-    Nop,
-    Acc,
-    Jmp,
+use std::{
+    collections::{BTreeSet, HashMap, HashSet},
+    convert::TryFrom,
+    fmt,
+    str::FromStr,
+};
+pub const REGISTERS: usize = 8;
+const REGISTER_NAMES: [&str; REGISTERS] = ["acc", "a", "b", "c", "d", "e", "f", "g"];
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Register(usize);
+impl Register {
+    pub const ACC: Register = Register(0);
 }
-#[derive(Debug)]
-struct Instruction {
-    command: Command,
+impl FromStr for Register {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        REGISTER_NAMES
+            .iter()
+            .position(|x| *x == s)
+            .map(Register)
+            .ok_or(ParseError("Unknown register"))
+    }
+}
+impl fmt::Display for Register {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", REGISTER_NAMES[self.0])
+    }
+}
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Operands {
+    None,
+    Arg,
+    Register,
+    RegisterArg,
+}
+impl Operands {
+    fn count(&self) -> usize {
+        match self {
+            Operands::None => 0,
+            Operands::Arg | Operands::Register => 1,
+            Operands::RegisterArg => 2,
+        }
+    }
+}
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Control {
+    Next,
+    Jump,
+    Branch,
+    Halt,
+}
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Flow {
+    Next,
+    Jump(i32),
+    Halt,
+    Overflow,
+}
+#[derive(Debug, Clone, Default)]
+pub struct State {
+    pub registers: [i32; REGISTERS],
+    pub output: Vec<i32>,
+}
+impl State {
+    pub fn get(&self, register: Register) -> i32 {
+        self.registers[register.0]
+    }
+    pub fn set(&mut self, register: Register, value: i32) {
+        self.registers[register.0] = value;
+    }
+    pub fn add(&mut self, register: Register, arg: i32) -> Flow {
+        match self.get(register).checked_add(arg) {
+            Some(value) => {
+                self.set(register, value);
+                Flow::Next
+            }
+            None => Flow::Overflow,
+        }
+    }
+}
+pub struct Opcode {
+    pub mnemonic: &'static str,
+    pub code: u8,
+    pub operands: Operands,
+    pub control: Control,
+    pub flip: Option<&'static Opcode>,
+    pub execute: fn(Register, i32, &mut State) -> Flow,
+}
+impl fmt::Debug for Opcode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Opcode({})", self.mnemonic)
+    }
+}
+pub static NOP: Opcode = Opcode {
+    mnemonic: "nop",
+    code: 0,
+    operands: Operands::Arg,
+    control: Control::Next,
+    flip: Some(&JMP),
+    execute: |_, _, _| Flow::Next,
+};
+pub static ACC: Opcode = Opcode {
+    mnemonic: "acc",
+    code: 1,
+    operands: Operands::Arg,
+    control: Control::Next,
+    flip: None,
+    execute: |_, arg, state| state.add(Register::ACC, arg),
+};
+pub static JMP: Opcode = Opcode {
+    mnemonic: "jmp",
+    code: 2,
+    operands: Operands::Arg,
+    control: Control::Jump,
+    flip: Some(&NOP),
+    execute: |_, arg, _| Flow::Jump(arg),
+};
+pub static ADD: Opcode = Opcode {
+    mnemonic: "add",
+    code: 3,
+    operands: Operands::RegisterArg,
+    control: Control::Next,
+    flip: None,
+    execute: |register, arg, state| state.add(register, arg),
+};
+pub static JNZ: Opcode = Opcode {
+    mnemonic: "jnz",
+    code: 4,
+    operands: Operands::RegisterArg,
+    control: Control::Branch,
+    flip: None,
+    execute: |register, arg, state| {
+        if state.get(register) != 0 {
+            Flow::Jump(arg)
+        } else {
+            Flow::Next
+        }
+    },
+};
+pub static JEZ: Opcode = Opcode {
+    mnemonic: "jez",
+    code: 5,
+    operands: Operands::RegisterArg,
+    control: Control::Branch,
+    flip: None,
+    execute: |register, arg, state| {
+        if state.get(register) == 0 {
+            Flow::Jump(arg)
+        } else {
+            Flow::Next
+        }
+    },
+};
+pub static OUT: Opcode = Opcode {
+    mnemonic: "out",
+    code: 6,
+    operands: Operands::Register,
+    control: Control::Next,
+    flip: None,
+    execute: |register, _, state| {
+        state.output.push(state.get(register));
+        Flow::Next
+    },
+};
+pub static HALT: Opcode = Opcode {
+    mnemonic: "halt",
+    code: 7,
+    operands: Operands::None,
+    control: Control::Halt,
+    flip: None,
+    execute: |_, _, _| Flow::Halt,
+};
+#[derive(Debug, Clone)]
+pub struct InstructionSet {
+    opcodes: Vec<&'static Opcode>,
+}
+impl InstructionSet {
+    pub fn handheld() -> InstructionSet {
+        InstructionSet {
+            opcodes: vec![&NOP, &ACC, &JMP],
+        }
+    }
+    pub fn extended() -> InstructionSet {
+        InstructionSet::handheld()
+            .with_opcode(&ADD)
+            .with_opcode(&JNZ)
+            .with_opcode(&JEZ)
+            .with_opcode(&OUT)
+            .with_opcode(&HALT)
+    }
+    pub fn with_opcode(mut self, opcode: &'static Opcode) -> InstructionSet {
+        self.opcodes
+            .retain(|x| x.mnemonic != opcode.mnemonic && x.code != opcode.code);
+        self.opcodes.push(opcode);
+        self
+    }
+    pub fn opcode(&self, mnemonic: &str) -> Option<&'static Opcode> {
+        self.opcodes
+            .iter()
+            .copied()
+            .find(|x| x.mnemonic == mnemonic)
+    }
+    fn opcode_by_code(&self, code: u8) -> Option<&'static Opcode> {
+        self.opcodes.iter().copied().find(|x| x.code == code)
+    }
+    pub fn parse(&self, s: &str) -> Result<Instruction, ParseError> {
+        let tokens = s.split(' ').collect::<Vec<_>>();
+        let (mnemonic, operands) = tokens.split_first().unwrap();
+        if !self
+            .opcodes
+            .iter()
+            .any(|x| x.operands.count() == operands.len())
+        {
+            return Err(ParseError("Could not parse instruction"));
+        }
+        let opcode = self
+            .opcode(mnemonic)
+            .ok_or(ParseError("Unknown instruction"))?;
+        let (register, arg) = match (opcode.operands, operands) {
+            (Operands::None, []) => (Register::ACC, 0),
+            (Operands::Arg, [arg]) => (Register::ACC, parse_arg(arg)?),
+            (Operands::Register, [register]) => (register.parse()?, 0),
+            (Operands::RegisterArg, [register, arg]) => (register.parse()?, parse_arg(arg)?),
+            _ => return Err(ParseError("Could not parse instruction")),
+        };
+        Ok(Instruction::new(opcode, register, arg))
+    }
+}
+fn parse_arg(s: &str) -> Result<i32, ParseError> {
+    s.parse().or(Err(ParseError("Could not parse arg")))
+}
+#[derive(Debug, Clone, Copy)]
+pub struct Instruction {
+    opcode: &'static Opcode,
+    register: Register,
     arg: i32,
 }
+impl PartialEq for Instruction {
+    fn eq(&self, other: &Self) -> bool {
+        self.opcode.mnemonic == other.opcode.mnemonic
+            && self.register == other.register
+            && self.arg == other.arg
+    }
+}
+impl Instruction {
+    pub fn new(opcode: &'static Opcode, register: Register, arg: i32) -> Instruction {
+        let (register, arg) = match opcode.operands {
+            Operands::None => (Register::ACC, 0),
+            Operands::Arg => (Register::ACC, arg),
+            Operands::Register => (register, 0),
+            Operands::RegisterArg => (register, arg),
+        };
+        Instruction {
+            opcode,
+            register,
+            arg,
+        }
+    }
+    pub fn opcode(&self) -> &'static Opcode {
+        self.opcode
+    }
+    pub fn register(&self) -> Register {
+        self.register
+    }
+    pub fn arg(&self) -> i32 {
+        self.arg
+    }
+    fn execute(&self, state: &mut State) -> Flow {
+        (self.opcode.execute)(self.register, self.arg, state)
+    }
+    fn successors(&self, pointer: usize, len: usize) -> Vec<i64> {
+        let next = pointer as i64 + 1;
+        let target = pointer as i64 + self.arg as i64;
+        match self.opcode.control {
+            Control::Next => vec![next],
+            Control::Jump => vec![target],
+            Control::Branch => vec![next, target],
+            Control::Halt => vec![len as i64],
+        }
+    }
+    pub fn flipped(&self) -> Option<Instruction> {
+        self.opcode
+            .flip
+            .map(|opcode| Instruction::new(opcode, self.register, self.arg))
+    }
+}
 impl FromStr for Instruction {
     type Err = ParseError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        if let [instruction, argument] = s.split(' ').collect::<Vec<_>>()[..] {
-            let arg = argument
-                .parse()
-                .or(Err(ParseError("Could not parse arg")))?;
-            let command = match instruction {
-                "nop" => Ok(Command::Nop),
-                "acc" => Ok(Command::Acc),
-                "jmp" => Ok(Command::Jmp),
-                _ => Err(ParseError("Unknown instruction")),
-            }?;
-            Ok(Instruction { command, arg })
-        } else {
-            Err(ParseError("Could not parse instruction"))
+        InstructionSet::handheld().parse(s)
+    }
+}
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mnemonic = self.opcode.mnemonic;
+        match self.opcode.operands {
+            Operands::None => write!(f, "{}", mnemonic),
+            Operands::Arg => write!(f, "{} {:+}", mnemonic, self.arg),
+            Operands::Register => write!(f, "{} {}", mnemonic, self.register),
+            Operands::RegisterArg => write!(f, "{} {} {:+}", mnemonic, self.register, self.arg),
         }
     }
 }
-#[derive(Debug)]
-struct Program(Vec<Instruction>);
-#[derive(Debug)]
-struct ParseError(&'static str);
-enum ProgramResult {
+#[derive(Debug, Clone, PartialEq)]
+pub struct Program(Vec<Instruction>);
+#[derive(Debug, PartialEq)]
+pub struct ParseError(&'static str);
+#[derive(Debug, PartialEq)]
+pub struct EncodeError(&'static str);
+#[derive(Debug, PartialEq)]
+pub struct AssemblyError {
+    pub reason: &'static str,
+    pub line: usize,
+}
+impl fmt::Display for AssemblyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} at line {}", self.reason, self.line)
+    }
+}
+const BINARY_MAGIC: &[u8] = b"HHC1";
+pub const DEFAULT_STEP_LIMIT: usize = 1_000_000;
+#[derive(Debug, PartialEq)]
+pub enum ProgramResult {
     Terminates(i32),
     Loops(i32),
+    JumpedBeforeStart { acc: i32, pointer: i64 },
+    JumpedBeyondEnd { acc: i32, pointer: i64 },
+    StepLimitExceeded { acc: i32, pointer: i64 },
+    Overflow { acc: i32, pointer: i64 },
+}
+#[derive(Debug, Clone, PartialEq)]
+pub struct ControlFlowGraph {
+    instructions: Vec<Instruction>,
+    successors: Vec<Vec<usize>>,
+}
+impl ControlFlowGraph {
+    pub fn len(&self) -> usize {
+        self.instructions.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.instructions.is_empty()
+    }
+    pub fn successors(&self, pointer: usize) -> &[usize] {
+        &self.successors[pointer]
+    }
+    pub fn reachable(&self) -> Vec<bool> {
+        let mut reachable = vec![false; self.len() + 1];
+        let mut queue = vec![0];
+        while let Some(pointer) = queue.pop() {
+            if reachable[pointer] {
+                continue;
+            }
+            reachable[pointer] = true;
+            if pointer < self.len() {
+                queue.extend(self.successors[pointer].iter().copied());
+            }
+        }
+        reachable
+    }
+    pub fn unreachable_instructions(&self) -> Vec<usize> {
+        let reachable = self.reachable();
+        (0..self.len()).filter(|x| !reachable[*x]).collect()
+    }
+    pub fn can_terminate(&self) -> Vec<bool> {
+        let len = self.len();
+        let mut predecessors = vec![vec![]; len + 1];
+        for (pointer, targets) in self.successors.iter().enumerate() {
+            for &target in targets {
+                predecessors[target].push(pointer);
+            }
+        }
+        let mut can_terminate = vec![false; len + 1];
+        can_terminate[len] = true;
+        let mut queue = vec![len];
+        while let Some(pointer) = queue.pop() {
+            for &predecessor in predecessors[pointer].iter() {
+                if !can_terminate[predecessor] {
+                    can_terminate[predecessor] = true;
+                    queue.push(predecessor);
+                }
+            }
+        }
+        can_terminate
+    }
+    pub fn terminating_instructions(&self) -> BTreeSet<usize> {
+        let can_terminate = self.can_terminate();
+        (0..self.len()).filter(|x| can_terminate[*x]).collect()
+    }
+    pub fn strongly_connected_components(&self) -> Vec<Vec<usize>> {
+        let len = self.len();
+        let mut index = vec![usize::MAX; len];
+        let mut lowlink = vec![0; len];
+        let mut on_stack = vec![false; len];
+        let mut stack = vec![];
+        let mut components = vec![];
+        let mut next_index = 0;
+        for root in 0..len {
+            if index[root] != usize::MAX {
+                continue;
+            }
+            index[root] = next_index;
+            lowlink[root] = next_index;
+            next_index += 1;
+            stack.push(root);
+            on_stack[root] = true;
+            let mut call_stack = vec![(root, 0)];
+            while let Some(&(pointer, edge)) = call_stack.last() {
+                let targets = &self.successors[pointer];
+                if edge < targets.len() {
+                    call_stack.last_mut().unwrap().1 += 1;
+                    let target = targets[edge];
+                    if target == len {
+                        continue;
+                    }
+                    if index[target] == usize::MAX {
+                        index[target] = next_index;
+                        lowlink[target] = next_index;
+                        next_index += 1;
+                        stack.push(target);
+                        on_stack[target] = true;
+                        call_stack.push((target, 0));
+                    } else if on_stack[target] {
+                        lowlink[pointer] = lowlink[pointer].min(index[target]);
+                    }
+                } else {
+                    call_stack.pop();
+                    if let Some(&(parent, _)) = call_stack.last() {
+                        lowlink[parent] = lowlink[parent].min(lowlink[pointer]);
+                    }
+                    if lowlink[pointer] == index[pointer] {
+                        let mut component = vec![];
+                        while let Some(member) = stack.pop() {
+                            on_stack[member] = false;
+                            component.push(member);
+                            if member == pointer {
+                                break;
+                            }
+                        }
+                        component.sort_unstable();
+                        components.push(component);
+                    }
+                }
+            }
+        }
+        components
+    }
+    pub fn loops(&self) -> Vec<Vec<usize>> {
+        self.strongly_connected_components()
+            .into_iter()
+            .filter(|x| x.len() > 1 || self.successors[x[0]].contains(&x[0]))
+            .collect()
+    }
+    pub fn to_dot(&self) -> String {
+        let reachable = self.reachable();
+        let can_terminate = self.can_terminate();
+        let mut dot = String::from("digraph program {\n");
+        for (pointer, instruction) in self.instructions.iter().enumerate() {
+            let mut attributes = vec![format!("label=\"{}: {}\"", pointer, instruction)];
+            if !reachable[pointer] {
+                attributes.push("style=dashed".to_string());
+            }
+            if !can_terminate[pointer] {
+                attributes.push("color=red".to_string());
+            }
+            dot.push_str(&format!("    {} [{}];\n", pointer, attributes.join(", ")));
+        }
+        dot.push_str("    end [shape=doublecircle];\n");
+        for (pointer, targets) in self.successors.iter().enumerate() {
+            for &target in targets {
+                if target == self.len() {
+                    dot.push_str(&format!("    {} -> end;\n", pointer));
+                } else {
+                    dot.push_str(&format!("    {} -> {};\n", pointer, target));
+                }
+            }
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Repair {
+    pub pointer: usize,
+    pub original: Instruction,
+    pub replacement: Instruction,
+}
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RepairError {
+    AlreadyTerminates,
+    ConditionalJumps,
+    NoRepair,
+}
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MachineStatus {
+    Running,
+    Breakpoint(usize),
+    Terminated,
+    Looped,
+    JumpedBeforeStart(i64),
+    JumpedBeyondEnd(i64),
+    StepLimitExceeded,
+    Overflow,
+}
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TraceEntry {
+    pub step: usize,
+    pub pointer: usize,
+    pub instruction: Instruction,
+    pub acc: i32,
+}
+#[derive(Debug)]
+pub struct Machine<'a> {
+    program: &'a Program,
+    pointer: usize,
+    state: State,
+    halted: bool,
+    overflowed: bool,
+    invalid_jump: Option<i64>,
+    steps: usize,
+    step_limit: Option<usize>,
+    visited: Vec<bool>,
+    seen_states: Option<HashSet<(usize, [i32; REGISTERS])>>,
+    breakpoints: BTreeSet<usize>,
+    paused_at: Option<usize>,
+    tracing: bool,
+    trace: Vec<TraceEntry>,
+}
+impl<'a> Machine<'a> {
+    pub fn new(program: &'a Program) -> Machine<'a> {
+        Machine {
+            program,
+            pointer: 0,
+            state: State::default(),
+            halted: false,
+            overflowed: false,
+            invalid_jump: None,
+            step_limit: None,
+            steps: 0,
+            visited: vec![false; program.0.len()],
+            seen_states: if program.has_conditional_jumps() {
+                Some(HashSet::new())
+            } else {
+                None
+            },
+            breakpoints: BTreeSet::new(),
+            paused_at: None,
+            tracing: false,
+            trace: vec![],
+        }
+    }
+    pub fn with_trace(mut self) -> Self {
+        self.tracing = true;
+        self
+    }
+    pub fn with_step_limit(mut self, step_limit: usize) -> Self {
+        self.step_limit = Some(step_limit);
+        self
+    }
+    pub fn pointer(&self) -> usize {
+        self.pointer
+    }
+    pub fn acc(&self) -> i32 {
+        self.state.get(Register::ACC)
+    }
+    pub fn register(&self, register: Register) -> i32 {
+        self.state.get(register)
+    }
+    pub fn output(&self) -> &[i32] {
+        &self.state.output
+    }
+    pub fn steps(&self) -> usize {
+        self.steps
+    }
+    pub fn trace(&self) -> &[TraceEntry] {
+        &self.trace
+    }
+    pub fn add_breakpoint(&mut self, pointer: usize) {
+        self.breakpoints.insert(pointer);
+    }
+    pub fn remove_breakpoint(&mut self, pointer: usize) {
+        self.breakpoints.remove(&pointer);
+    }
+    pub fn status(&self) -> MachineStatus {
+        if let Some(target) = self.invalid_jump {
+            if target < 0 {
+                MachineStatus::JumpedBeforeStart(target)
+            } else {
+                MachineStatus::JumpedBeyondEnd(target)
+            }
+        } else if self.overflowed {
+            MachineStatus::Overflow
+        } else if self.halted || self.pointer == self.program.0.len() {
+            MachineStatus::Terminated
+        } else if self
+            .seen_states
+            .as_ref()
+            .map_or(self.visited[self.pointer], |x| {
+                x.contains(&(self.pointer, self.state.registers))
+            })
+        {
+            MachineStatus::Looped
+        } else if self.step_limit.is_some_and(|x| self.steps >= x) {
+            MachineStatus::StepLimitExceeded
+        } else {
+            MachineStatus::Running
+        }
+    }
+    pub fn step(&mut self) -> MachineStatus {
+        let status = self.status();
+        if status != MachineStatus::Running {
+            return status;
+        }
+        self.visited[self.pointer] = true;
+        if let Some(seen_states) = self.seen_states.as_mut() {
+            seen_states.insert((self.pointer, self.state.registers));
+        }
+        let instruction = self.program.0[self.pointer];
+        let pointer = self.pointer;
+        match instruction.execute(&mut self.state) {
+            Flow::Next => self.pointer += 1,
+            Flow::Jump(arg) => {
+                let target = self.pointer as i64 + arg as i64;
+                match usize::try_from(target) {
+                    Ok(target) if target <= self.program.0.len() => self.pointer = target,
+                    _ => self.invalid_jump = Some(target),
+                }
+            }
+            Flow::Halt => self.halted = true,
+            Flow::Overflow => self.overflowed = true,
+        };
+        self.steps += 1;
+        if self.tracing {
+            self.trace.push(TraceEntry {
+                step: self.steps,
+                pointer,
+                instruction,
+                acc: self.acc(),
+            });
+        }
+        self.status()
+    }
+    pub fn run(&mut self) -> MachineStatus {
+        loop {
+            let status = self.status();
+            if status != MachineStatus::Running {
+                return status;
+            }
+            if self.breakpoints.contains(&self.pointer) && self.paused_at != Some(self.pointer) {
+                self.paused_at = Some(self.pointer);
+                return MachineStatus::Breakpoint(self.pointer);
+            }
+            self.paused_at = None;
+            self.step();
+        }
+    }
+    pub fn format_trace(&self) -> String {
+        self.trace
+            .iter()
+            .map(|x| {
+                format!(
+                    "{:>5} {:>5}: {:<8} acc={}\n",
+                    x.step,
+                    x.pointer,
+                    x.instruction.to_string(),
+                    x.acc
+                )
+            })
+            .collect()
+    }
 }
 impl Program {
-    fn from_string(input: Vec<String>) -> Result<Program, ParseError> {
+    pub fn from_string(input: Vec<String>) -> Result<Program, ParseError> {
         let instructions = input
             .iter()
             .map(|x| x.parse())
             .collect::<Result<Vec<_>, _>>()?;
         Ok(Program(instructions))
     }
-    fn run(&self) -> ProgramResult {
+    pub fn from_string_with_instruction_set(
+        input: Vec<String>,
+        instruction_set: &InstructionSet,
+    ) -> Result<Program, ParseError> {
+        let instructions = input
+            .iter()
+            .map(|x| instruction_set.parse(x))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Program(instructions))
+    }
+    pub fn run(&self) -> ProgramResult {
+        self.run_with_step_limit(DEFAULT_STEP_LIMIT.max(self.0.len()))
+    }
+    pub fn run_with_step_limit(&self, step_limit: usize) -> ProgramResult {
+        Program::result_of(Machine::new(self).with_step_limit(step_limit))
+    }
+    fn result_of(mut machine: Machine) -> ProgramResult {
+        let status = machine.run();
+        let acc = machine.acc();
+        match status {
+            MachineStatus::Terminated => ProgramResult::Terminates(acc),
+            MachineStatus::JumpedBeforeStart(pointer) => {
+                ProgramResult::JumpedBeforeStart { acc, pointer }
+            }
+            MachineStatus::JumpedBeyondEnd(pointer) => {
+                ProgramResult::JumpedBeyondEnd { acc, pointer }
+            }
+            MachineStatus::StepLimitExceeded => ProgramResult::StepLimitExceeded {
+                acc,
+                pointer: machine.pointer() as i64,
+            },
+            MachineStatus::Overflow => ProgramResult::Overflow {
+                acc,
+                pointer: machine.pointer() as i64,
+            },
+            _ => ProgramResult::Loops(acc),
+        }
+    }
+    fn has_conditional_jumps(&self) -> bool {
+        self.0.iter().any(|x| x.opcode.control == Control::Branch)
+    }
+    pub fn control_flow_graph(&self) -> ControlFlowGraph {
+        let len = self.0.len();
+        let successors = self
+            .0
+            .iter()
+            .enumerate()
+            .map(|(pointer, instruction)| {
+                let mut targets = instruction
+                    .successors(pointer, len)
+                    .into_iter()
+                    .filter(|x| (0..=len as i64).contains(x))
+                    .map(|x| x as usize)
+                    .collect::<Vec<_>>();
+                targets.dedup();
+                targets
+            })
+            .collect();
+        ControlFlowGraph {
+            instructions: self.0.clone(),
+            successors,
+        }
+    }
+    pub fn repair_candidates(&self) -> Result<Vec<Repair>, RepairError> {
+        if self.has_conditional_jumps() {
+            return Err(RepairError::ConditionalJumps);
+        }
+        let len = self.0.len();
+        let can_terminate = self.control_flow_graph().can_terminate();
+        if can_terminate[0] {
+            return Err(RepairError::AlreadyTerminates);
+        }
+        let mut visited = vec![false; len];
         let mut pointer = 0;
-        let mut acc = 0;
-        let mut visited = BTreeSet::new();
-        loop {
-            if pointer == self.0.len() {
-                return ProgramResult::Terminates(acc);
-            } else if visited.contains(&pointer) {
-                return ProgramResult::Loops(acc);
-            } else {
-                visited.insert(pointer);
-            }
-            match self.0[pointer] {
-                Instruction {
-                    command: Command::Nop,
-                    arg: _,
-                } => {
-                    pointer += 1;
+        let mut candidates = vec![];
+        while pointer < len && !visited[pointer] {
+            visited[pointer] = true;
+            let original = self.0[pointer];
+            if let Some(replacement) = original.flipped() {
+                let target = replacement.successors(pointer, len)[0];
+                if (0..=len as i64).contains(&target) && can_terminate[target as usize] {
+                    candidates.push(Repair {
+                        pointer,
+                        original,
+                        replacement,
+                    });
                 }
-                Instruction {
-                    command: Command::Acc,
-                    arg,
-                } => {
-                    acc += arg;
-                    pointer += 1;
-                }
-                Instruction {
-                    command: Command::Jmp,
-                    arg,
-                } => {
-                    pointer = usize::try_from(pointer as i32 + arg).unwrap();
-                }
-            };
+            }
+            match original.successors(pointer, len)[0] {
+                target if (0..len as i64).contains(&target) => pointer = target as usize,
+                _ => break,
+            }
+        }
+        if candidates.is_empty() {
+            Err(RepairError::NoRepair)
+        } else {
+            Ok(candidates)
         }
     }
-    fn heal(&mut self) {
-        let mut heal_pointer = 0;
-        loop {
-            if self.0[heal_pointer].command == Command::Nop {
-                self.0[heal_pointer].command = Command::Jmp;
-                if let ProgramResult::Terminates(_) = self.run() {
-                    return ();
-                } else {
-                    self.0[heal_pointer].command = Command::Nop;
+    pub fn repaired(&self) -> Result<Program, RepairError> {
+        let repair = self.repair_candidates()?[0];
+        let mut program = self.clone();
+        program.0[repair.pointer] = repair.replacement;
+        Ok(program)
+    }
+}
+
+impl fmt::Display for Program {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for instruction in self.0.iter() {
+            writeln!(f, "{}", instruction)?;
+        }
+        Ok(())
+    }
+}
+fn write_varint(bytes: &mut Vec<u8>, mut value: u32) {
+    while value >= 0x80 {
+        bytes.push((value & 0x7f) as u8 | 0x80);
+        value >>= 7;
+    }
+    bytes.push(value as u8);
+}
+fn read_varint(bytes: &mut impl Iterator<Item = u8>) -> Result<u32, ParseError> {
+    let mut value = 0u32;
+    for shift in (0..35).step_by(7) {
+        let byte = bytes.next().ok_or(ParseError("Unexpected end of input"))?;
+        if shift == 28 && byte > 0x0f {
+            return Err(ParseError("Varint overflow"));
+        }
+        value |= ((byte & 0x7f) as u32) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    Err(ParseError("Varint overflow"))
+}
+fn is_label(s: &str) -> bool {
+    let mut chars = s.chars();
+    chars
+        .next()
+        .is_some_and(|x| x.is_ascii_alphabetic() || x == '_')
+        && chars.all(|x| x.is_ascii_alphanumeric() || x == '_')
+}
+impl Program {
+    pub fn assemble(
+        source: &str,
+        instruction_set: &InstructionSet,
+    ) -> Result<Program, AssemblyError> {
+        let mut labels = HashMap::new();
+        let mut lines = vec![];
+        for (line_number, line) in source.lines().enumerate().map(|(i, x)| (i + 1, x)) {
+            let mut line = line.split([';', '#']).next().unwrap().trim();
+            while let Some((label, rest)) = line.split_once(':') {
+                let label = label.trim();
+                if !is_label(label) {
+                    return Err(AssemblyError {
+                        reason: "Invalid label",
+                        line: line_number,
+                    });
                 }
-            } else if self.0[heal_pointer].command == Command::Jmp {
-                self.0[heal_pointer].command = Command::Nop;
-                if let ProgramResult::Terminates(_) = self.run() {
-                    return ();
-                } else {
-                    self.0[heal_pointer].command = Command::Jmp;
+                if labels.insert(label, lines.len()).is_some() {
+                    return Err(AssemblyError {
+                        reason: "Duplicate label",
+                        line: line_number,
+                    });
                 }
+                line = rest.trim();
+            }
+            if !line.is_empty() {
+                lines.push((line_number, line));
             }
-            heal_pointer += 1;
         }
+        let instructions = lines
+            .iter()
+            .enumerate()
+            .map(|(pointer, (line_number, line))| {
+                let mut tokens = line.split_whitespace().collect::<Vec<_>>();
+                let target;
+                let takes_target = instruction_set.opcode(tokens[0]).is_some_and(|x| {
+                    matches!(x.operands, Operands::Arg | Operands::RegisterArg)
+                        && (x.control != Control::Next || x.flip.is_some())
+                });
+                if let (true, Some(&label)) = (takes_target, tokens.last()) {
+                    if is_label(label) {
+                        let destination = labels.get(label).ok_or(AssemblyError {
+                            reason: "Undefined label",
+                            line: *line_number,
+                        })?;
+                        target = format!("{:+}", *destination as i64 - pointer as i64);
+                        *tokens.last_mut().unwrap() = &target;
+                    }
+                }
+                instruction_set
+                    .parse(&tokens.join(" "))
+                    .map_err(|ParseError(reason)| AssemblyError {
+                        reason,
+                        line: *line_number,
+                    })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Program(instructions))
+    }
+    pub fn to_bytes(&self) -> Result<Vec<u8>, EncodeError> {
+        let len = u32::try_from(self.0.len()).or(Err(EncodeError("Program is too long")))?;
+        let mut bytes = BINARY_MAGIC.to_vec();
+        write_varint(&mut bytes, len);
+        for instruction in self.0.iter() {
+            let opcode = instruction.opcode;
+            if opcode.code > 0x1f {
+                return Err(EncodeError("Opcode code does not fit in five bits"));
+            }
+            bytes.push(opcode.code | ((instruction.register.0 as u8) << 5));
+            if matches!(opcode.operands, Operands::Arg | Operands::RegisterArg) {
+                let arg = instruction.arg;
+                write_varint(&mut bytes, ((arg << 1) ^ (arg >> 31)) as u32);
+            }
+        }
+        Ok(bytes)
+    }
+    pub fn from_bytes(bytes: &[u8]) -> Result<Program, ParseError> {
+        Program::from_bytes_with_instruction_set(bytes, &InstructionSet::extended())
+    }
+    pub fn from_bytes_with_instruction_set(
+        bytes: &[u8],
+        instruction_set: &InstructionSet,
+    ) -> Result<Program, ParseError> {
+        if !bytes.starts_with(BINARY_MAGIC) {
+            return Err(ParseError("Missing header"));
+        }
+        let mut bytes = bytes[BINARY_MAGIC.len()..].iter().copied();
+        let len = read_varint(&mut bytes)?;
+        let mut instructions = vec![];
+        for _ in 0..len {
+            let byte = bytes.next().ok_or(ParseError("Unexpected end of input"))?;
+            let register = Register((byte >> 5) as usize);
+            let opcode = instruction_set
+                .opcode_by_code(byte & 0x1f)
+                .ok_or(ParseError("Unknown instruction"))?;
+            if register != Register::ACC
+                && !matches!(opcode.operands, Operands::Register | Operands::RegisterArg)
+            {
+                return Err(ParseError("Unexpected register"));
+            }
+            let arg = if matches!(opcode.operands, Operands::Arg | Operands::RegisterArg) {
+                read_varint(&mut bytes).map(|x| ((x >> 1) as i32) ^ -((x & 1) as i32))?
+            } else {
+                0
+            };
+            let instruction = Instruction::new(opcode, register, arg);
+            instructions.push(instruction);
+        }
+        if bytes.next().is_some() {
+            return Err(ParseError("Trailing data"));
+        }
+        Ok(Program(instructions))
     }
 }
 
@@ -112,8 +944,7 @@ pub fn puzzle1(input: Vec<String>) -> i32 {
     }
 }
 pub fn puzzle2(input: Vec<String>) -> i32 {
-    let mut program = Program::from_string(input).unwrap();
-    program.heal();
+    let program = Program::from_string(input).unwrap().repaired().unwrap();
     if let ProgramResult::Terminates(result) = program.run() {
         result
     } else {
@@ -125,6 +956,14 @@ pub fn puzzle2(input: Vec<String>) -> i32 {
 mod tests {
     use super::*;
 
+    fn program(input: &[&str], instruction_set: &InstructionSet) -> Program {
+        Program::from_string_with_instruction_set(
+            input.iter().map(|x| x.to_string()).collect(),
+            instruction_set,
+        )
+        .unwrap()
+    }
+
     #[test]
     fn first_puzzle_test_input() {
         assert_eq!(
@@ -157,6 +996,391 @@ mod tests {
         );
     }
 
+    #[test]
+    fn step_through_program() {
+        let program =
+            Program::from_string(TEST_INPUT.iter().map(|x| x.to_string()).collect()).unwrap();
+        let mut machine = Machine::new(&program).with_trace();
+        assert_eq!(machine.step(), MachineStatus::Running);
+        assert_eq!(machine.step(), MachineStatus::Running);
+        assert_eq!(
+            (machine.pointer(), machine.acc(), machine.steps()),
+            (2, 1, 2)
+        );
+        machine.add_breakpoint(4);
+        assert_eq!(machine.run(), MachineStatus::Breakpoint(4));
+        assert_eq!(machine.pointer(), 4);
+        assert_eq!(machine.acc(), 5);
+        assert_eq!(machine.run(), MachineStatus::Looped);
+        assert_eq!(machine.acc(), 5);
+        assert_eq!(machine.step(), MachineStatus::Looped);
+        assert_eq!(
+            machine
+                .trace()
+                .iter()
+                .map(|x| x.pointer)
+                .collect::<Vec<_>>(),
+            vec![0, 1, 2, 6, 7, 3, 4]
+        );
+        assert_eq!(
+            machine.format_trace().lines().nth(3),
+            Some("    4     6: acc +1   acc=2")
+        );
+    }
+
+    #[test]
+    fn handheld_instruction_set_rejects_extensions() {
+        assert!("add a +1".parse::<Instruction>().is_err());
+        assert!("halt".parse::<Instruction>().is_err());
+        assert!("nop +0 +1".parse::<Instruction>().is_err());
+        assert_eq!(
+            "jmp -3".parse::<Instruction>().unwrap(),
+            Instruction::new(&JMP, Register::ACC, -3)
+        );
+    }
+
+    #[test]
+    fn extended_instruction_set() {
+        let input = vec![
+            "add a +3", "acc +2", "out a", "add a -1", "jnz a -3", "jez a +2", "acc +100",
+            "out acc", "halt", "acc +100",
+        ];
+        let program = Program::from_string_with_instruction_set(
+            input.iter().map(|x| x.to_string()).collect(),
+            &InstructionSet::extended(),
+        )
+        .unwrap();
+        let mut machine = Machine::new(&program);
+        assert_eq!(machine.run(), MachineStatus::Terminated);
+        assert_eq!(machine.output(), &[3, 2, 1, 6]);
+        let program = Program::from_string_with_instruction_set(
+            vec!["add a +0".to_string(), "jez a -1".to_string()],
+            &InstructionSet::extended(),
+        )
+        .unwrap();
+        let mut machine = Machine::new(&program);
+        assert_eq!(machine.run(), MachineStatus::Looped);
+        let input = vec![
+            "add b +3", "add c +1", "out b", "add b -1", "jnz b -2", "jez b +2", "acc +100",
+            "out c", "halt", "acc +100",
+        ];
+        let program = Program::from_string_with_instruction_set(
+            input.iter().map(|x| x.to_string()).collect(),
+            &InstructionSet::extended(),
+        )
+        .unwrap();
+        let mut machine = Machine::new(&program);
+        assert_eq!(machine.run(), MachineStatus::Terminated);
+        assert_eq!(machine.output(), &[3, 2, 1, 1]);
+        assert_eq!(machine.pointer(), 8);
+        assert_eq!(machine.acc(), 0);
+        assert_eq!(machine.register("b".parse().unwrap()), 0);
+        assert_eq!(program.0[4].to_string(), "jnz b -2");
+    }
+
+    #[test]
+    fn repair_candidates() {
+        let program =
+            Program::from_string(TEST_INPUT.iter().map(|x| x.to_string()).collect()).unwrap();
+        assert_eq!(
+            program.repair_candidates(),
+            Ok(vec![Repair {
+                pointer: 7,
+                original: Instruction::new(&JMP, Register::ACC, -4),
+                replacement: Instruction::new(&NOP, Register::ACC, -4),
+            }])
+        );
+        assert_eq!(
+            program.repaired().unwrap().run(),
+            ProgramResult::Terminates(8)
+        );
+        let unrepairable = Program::from_string(vec![
+            "jmp +0".to_string(),
+            "jmp -1".to_string(),
+            "jmp -2".to_string(),
+        ])
+        .unwrap();
+        assert_eq!(unrepairable.repair_candidates(), Err(RepairError::NoRepair));
+        let terminating = Program::from_string(vec!["acc +1".to_string()]).unwrap();
+        assert_eq!(terminating.repaired(), Err(RepairError::AlreadyTerminates));
+    }
+
+    #[test]
+    fn repair_real_input() {
+        let program =
+            Program::from_string(crate::util::read_file("./data/day8.txt").unwrap()).unwrap();
+        let candidates = program.repair_candidates().unwrap();
+        assert_eq!(candidates.len(), 1);
+        for candidate in candidates {
+            let mut repaired = program.clone();
+            repaired.0[candidate.pointer] = candidate.replacement;
+            assert_eq!(repaired.run(), ProgramResult::Terminates(1626));
+        }
+    }
+
+    #[test]
+    fn handheld_out_of_range_jumps() {
+        let handheld = InstructionSet::handheld();
+        assert_eq!(
+            program(&["acc +1", "jmp -2"], &handheld).run(),
+            ProgramResult::JumpedBeforeStart {
+                acc: 1,
+                pointer: -1
+            }
+        );
+        assert_eq!(
+            program(&["acc +1", "nop +0", "jmp +5", "acc +1"], &handheld).run(),
+            ProgramResult::JumpedBeyondEnd { acc: 1, pointer: 7 }
+        );
+        assert_eq!(
+            program(&["acc +1", "jmp +2", "acc +1"], &handheld).run(),
+            ProgramResult::Terminates(1)
+        );
+    }
+
+    #[test]
+    fn out_of_range_jumps() {
+        let extended = InstructionSet::extended();
+        assert_eq!(
+            program(&["acc +2", "jmp -5"], &extended).run(),
+            ProgramResult::JumpedBeforeStart {
+                acc: 2,
+                pointer: -4
+            }
+        );
+        assert_eq!(
+            program(&["acc +2", "jmp +3", "nop +0"], &extended).run(),
+            ProgramResult::JumpedBeyondEnd { acc: 2, pointer: 4 }
+        );
+        assert_eq!(
+            program(&["acc +2", "jmp +2", "nop +0"], &extended).run(),
+            ProgramResult::Terminates(2)
+        );
+        assert_eq!(
+            program(&["add a +1", "acc +1", "jnz a -2"], &extended).run_with_step_limit(10),
+            ProgramResult::StepLimitExceeded { acc: 3, pointer: 1 }
+        );
+    }
+
+    #[test]
+    fn control_flow_analysis() {
+        let program =
+            Program::from_string(TEST_INPUT.iter().map(|x| x.to_string()).collect()).unwrap();
+        let graph = program.control_flow_graph();
+        assert_eq!(graph.successors(2), &[6]);
+        assert_eq!(graph.successors(8), &[9]);
+        assert_eq!(graph.unreachable_instructions(), vec![5, 8]);
+        assert_eq!(graph.loops(), vec![vec![1, 2, 3, 4, 6, 7]]);
+        assert_eq!(graph.strongly_connected_components().len(), 4);
+        assert_eq!(
+            graph
+                .terminating_instructions()
+                .into_iter()
+                .collect::<Vec<_>>(),
+            vec![8]
+        );
+        let dot = graph.to_dot();
+        assert!(dot.starts_with("digraph program {\n"));
+        assert!(dot.contains("    5 [label=\"5: acc -99\", style=dashed, color=red];\n"));
+        assert!(dot.contains("    8 [label=\"8: acc +6\", style=dashed];\n"));
+        assert!(dot.contains("    7 -> 3;\n"));
+        assert!(dot.contains("    8 -> end;\n"));
+    }
+
+    #[test]
+    fn control_flow_analysis_of_deep_program() {
+        let mut input = vec!["nop +0".to_string(); 100_000];
+        input.push("jmp -100000".to_string());
+        let graph = Program::from_string(input).unwrap().control_flow_graph();
+        assert_eq!(graph.loops().len(), 1);
+        assert!(graph.terminating_instructions().is_empty());
+        assert!(graph.unreachable_instructions().is_empty());
+    }
+
+    #[test]
+    fn assemble_with_labels_and_comments() {
+        let source = "; count down from three
+            add a +3
+        loop: out a      # print the counter
+            add a -1
+            jnz a loop
+            jmp end
+            acc -99
+        end:
+        done: halt
+        ";
+        let program = Program::assemble(source, &InstructionSet::extended()).unwrap();
+        assert_eq!(
+            program.to_string(),
+            "add a +3\nout a\nadd a -1\njnz a -2\njmp +2\nacc -99\nhalt\n"
+        );
+        let mut machine = Machine::new(&program);
+        assert_eq!(machine.run(), MachineStatus::Terminated);
+        assert_eq!(machine.output(), &[3, 2, 1]);
+        assert_eq!(
+            Program::assemble("jmp nowhere", &InstructionSet::handheld()),
+            Err(AssemblyError {
+                reason: "Undefined label",
+                line: 1
+            })
+        );
+        assert_eq!(
+            Program::assemble("a: nop +0\na: acc +1", &InstructionSet::handheld()),
+            Err(AssemblyError {
+                reason: "Duplicate label",
+                line: 2
+            })
+        );
+        assert_eq!(
+            Program::assemble("nop +0\nhalt", &InstructionSet::handheld())
+                .unwrap_err()
+                .to_string(),
+            "Could not parse instruction at line 2"
+        );
+    }
+
+    #[test]
+    fn disassemble_round_trip() {
+        let program =
+            Program::from_string(crate::util::read_file("./data/day8.txt").unwrap()).unwrap();
+        let text = program.to_string();
+        assert_eq!(
+            Program::from_string(text.lines().map(|x| x.to_string()).collect()).unwrap(),
+            program
+        );
+        assert_eq!(
+            Program::assemble(&text, &InstructionSet::handheld()).unwrap(),
+            program
+        );
+    }
+
+    #[test]
+    fn binary_round_trip() {
+        let program =
+            Program::from_string(crate::util::read_file("./data/day8.txt").unwrap()).unwrap();
+        let bytes = program.to_bytes().unwrap();
+        assert!(bytes.len() < program.0.len() * 3);
+        assert_eq!(Program::from_bytes(&bytes).unwrap(), program);
+        let extended = Program::assemble(
+            "add g -2147483648\njez b +2147483647\nout f\nhalt",
+            &InstructionSet::extended(),
+        )
+        .unwrap();
+        assert_eq!(
+            Program::from_bytes(&extended.to_bytes().unwrap()).unwrap(),
+            extended
+        );
+        assert_eq!(
+            Program::from_bytes(&bytes[..bytes.len() - 1]),
+            Err(ParseError("Unexpected end of input"))
+        );
+        assert_eq!(
+            Program::from_bytes(b"nope"),
+            Err(ParseError("Missing header"))
+        );
+        let mut non_canonical = BINARY_MAGIC.to_vec();
+        non_canonical.extend(&[1, 2 | 1 << 5, 0]);
+        assert_eq!(
+            Program::from_bytes(&non_canonical),
+            Err(ParseError("Unexpected register"))
+        );
+        non_canonical[BINARY_MAGIC.len() + 1] = 2;
+        assert_eq!(
+            Program::from_bytes(&non_canonical).unwrap().to_string(),
+            "jmp +0\n"
+        );
+        assert_eq!(
+            Instruction::new(&HALT, Register(3), 7),
+            Instruction::new(&HALT, Register::ACC, 0)
+        );
+        static WIDE: Opcode = Opcode {
+            mnemonic: "wide",
+            code: 40,
+            operands: Operands::None,
+            control: Control::Next,
+            flip: None,
+            execute: |_, _, _| Flow::Next,
+        };
+        assert_eq!(
+            Program(vec![Instruction::new(&WIDE, Register::ACC, 0)]).to_bytes(),
+            Err(EncodeError("Opcode code does not fit in five bits"))
+        );
+    }
+
+    static DBL: Opcode = Opcode {
+        mnemonic: "dbl",
+        code: 8,
+        operands: Operands::Register,
+        control: Control::Next,
+        flip: None,
+        execute: |register, _, state| match state.get(register).checked_mul(2) {
+            Some(value) => {
+                state.set(register, value);
+                Flow::Next
+            }
+            None => Flow::Overflow,
+        },
+    };
+
+    #[test]
+    fn custom_opcode() {
+        let instruction_set = InstructionSet::extended().with_opcode(&DBL);
+        let input = ["add b +3", "dbl b", "out b", "dbl b", "out b", "jnz b -2"];
+        let program = Program::from_string_with_instruction_set(
+            input.iter().map(|x| x.to_string()).collect(),
+            &instruction_set,
+        )
+        .unwrap();
+        let mut machine = Machine::new(&program).with_step_limit(1000);
+        assert_eq!(machine.run(), MachineStatus::Overflow);
+        assert_eq!(machine.output()[..3], [6, 12, 24]);
+        assert_eq!(machine.pointer(), 3);
+        assert_eq!(program.to_string().lines().nth(1), Some("dbl b"));
+        assert_eq!(
+            Program::from_bytes_with_instruction_set(
+                &program.to_bytes().unwrap(),
+                &instruction_set
+            )
+            .unwrap(),
+            program
+        );
+        assert!(Program::from_bytes(&program.to_bytes().unwrap()).is_err());
+        assert!(InstructionSet::extended().parse("dbl b").is_err());
+        let overflowing = Program::from_string_with_instruction_set(
+            vec!["add a +1073741824".to_string(), "dbl a".to_string()],
+            &instruction_set,
+        )
+        .unwrap();
+        assert_eq!(
+            overflowing.run(),
+            ProgramResult::Overflow { acc: 0, pointer: 1 }
+        );
+    }
+
+    #[test]
+    fn runaway_programs_are_bounded() {
+        let extended = InstructionSet::extended();
+        assert_eq!(
+            program(&["add a +1", "jnz a -1"], &extended).run(),
+            ProgramResult::StepLimitExceeded {
+                acc: 0,
+                pointer: (DEFAULT_STEP_LIMIT % 2) as i64
+            }
+        );
+        assert_eq!(
+            program(&["acc +2147483647", "acc +1"], &extended).run(),
+            ProgramResult::Overflow {
+                acc: i32::MAX,
+                pointer: 1
+            }
+        );
+        let looping = program(&["acc +1", "jmp -1"], &extended);
+        let mut machine = Machine::new(&looping);
+        assert_eq!(machine.run(), MachineStatus::Looped);
+        assert!(machine.trace().is_empty());
+        assert_eq!(machine.format_trace(), "");
+    }
+
     const TEST_INPUT: &'static [&'static str] = &[
         "nop +0", "acc +1", "jmp +4", "acc +3", "jmp -3", "acc -99", "acc +1", "jmp -4", "acc +6",
     ];
@@ -6,6 +6,58 @@ use std::{
 
 pub fn read_file(file_name: &str) -> Result<Vec<String>, std::io::Error> {
     let path = Path::new(file_name);
-    let file = File::open(&path)?;
+    let file = File::open(path)?;
     io::BufReader::new(file).lines().collect()
 }
+
+pub fn group_records<S: AsRef<str>>(lines: &[S]) -> Vec<Vec<String>> {
+    group_records_with_lines(lines)
+        .into_iter()
+        .map(|(_, record)| record)
+        .collect()
+}
+
+pub fn group_records_with_lines<S: AsRef<str>>(lines: &[S]) -> Vec<(usize, Vec<String>)> {
+    let mut records: Vec<(usize, Vec<String>)> = vec![];
+    let mut in_record = false;
+    for (i, line) in lines.iter().enumerate() {
+        let line = line.as_ref().trim_end();
+        if line.is_empty() {
+            in_record = false;
+        } else if in_record {
+            if let Some((_, current_record)) = records.last_mut() {
+                current_record.push(line.to_string());
+            }
+        } else {
+            records.push((i + 1, vec![line.to_string()]));
+            in_record = true;
+        }
+    }
+    records
+}
+
+pub fn read_records(file_name: &str) -> Result<Vec<Vec<String>>, std::io::Error> {
+    Ok(group_records(&read_file(file_name)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn group_records_handles_blank_lines_and_line_endings() {
+        let input = ["", "a b\r", "c  ", "\r", "", " \t", "d", "e\r\n", "", ""];
+        assert_eq!(
+            group_records(&input),
+            vec![vec!["a b", "c"], vec!["d", "e"]]
+        );
+        assert_eq!(group_records::<&str>(&[]), Vec::<Vec<String>>::new());
+        assert_eq!(
+            group_records_with_lines(&input)
+                .iter()
+                .map(|(line, _)| *line)
+                .collect::<Vec<_>>(),
+            vec![2, 7]
+        );
+    }
+}